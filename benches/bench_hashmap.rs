@@ -5,7 +5,7 @@ use std::io::{BufRead, BufReader};
 use fnv::FnvHashMap;
 use memchr::memchr_iter;
 
-static WORDS: &'static [u8] = include_bytes!("/usr/share/dict/words");
+static WORDS: &[u8] = include_bytes!("/usr/share/dict/words");
 
 /*
 With GNU's miscfiles-1.5 web2 as a words file:
@@ -56,7 +56,7 @@ where
 			.unwrap();
 		let mut map = map(cap);
 		while let Some(line) = r.read_until(b'\n').unwrap() {
-			let p = prefix(&line, n);
+			let p = prefix(line, n);
 			insert(&mut map, p);
 		}
 		black_box(map);
@@ -100,7 +100,7 @@ fn baseline_hashmap(b: &mut Bencher, n: usize, cap: usize) {
 	let starts = vec![0].into_iter()
 		.chain(words.clone().into_iter().map(|n| n+1)); // one past delimiter
 	let ends = words.into_iter()
-		.chain(vec![WORDS.len()].into_iter());
+		.chain(vec![WORDS.len()]);
 	let lines: Vec<_> = starts.zip(ends)
 		.map(|(start, end)| &WORDS[start..end])
 		.collect();
@@ -108,7 +108,7 @@ fn baseline_hashmap(b: &mut Bencher, n: usize, cap: usize) {
 	b.iter(|| {
 		let mut map = map(cap);
 		for &line in lines.iter() {
-			let p = prefix(&line, n);
+			let p = prefix(line, n);
 			insert(&mut map, p);
 		}
 		black_box(map);
@@ -1,9 +1,9 @@
-use criterion::{black_box, criterion_group, criterion_main, Bencher, Criterion};
+use criterion::{black_box, criterion_group, criterion_main, BatchSize, Bencher, Criterion};
 
 use buf_ref_reader::*;
 use std::io::{Read, BufReader};
 
-static WORDS: &'static [u8] = include_bytes!("/usr/share/dict/words");
+static WORDS: &[u8] = include_bytes!("/usr/share/dict/words");
 
 // make sure we're blackboxing &[u8], not Vec<u8> or something else
 fn consume(data: &[u8]) {
@@ -30,11 +30,32 @@ fn bufref_read_vec_64x4(c: &mut Criterion)  { c.bench_function("bufref_read_vec_
 fn bufref_read_mmap_4x4(c: &mut Criterion)  { c.bench_function("bufref_read_mmap_4x4",  |b| bufref_read::<MmapBuffer>(b, 4096, 4)); }
 fn bufref_read_mmap_64x4(c: &mut Criterion) { c.bench_function("bufref_read_mmap_64x4", |b| bufref_read::<MmapBuffer>(b, 64*1024, 4)); }
 
+// same as above, but all the data is already buffered, so that only the cost of slicing the buffer is measured
+fn bufref_read_buffered<B: Buffer>(b: &mut Bencher, read: usize)
+where
+	B::Error: std::fmt::Debug,
+	Error: From<B::Error>,
+{
+	b.iter_batched_ref(|| {
+		let mut r = BufRefReaderBuilder::new(WORDS)
+			.capacity(WORDS.len())
+			.build::<B>()
+			.unwrap();
+		r.prefetch(WORDS.len()).unwrap();
+		r
+	}, |r| {
+		while let Some(chunk) = r.read(read).unwrap() {
+			consume(chunk);
+		}
+	}, BatchSize::LargeInput)
+}
+fn bufref_read_buffered_vec_4(c: &mut Criterion)  { c.bench_function("bufref_read_buffered_vec_4",  |b| bufref_read_buffered::<VecBuffer> (b, 4)); }
+fn bufref_read_buffered_mmap_4(c: &mut Criterion) { c.bench_function("bufref_read_buffered_mmap_4", |b| bufref_read_buffered::<MmapBuffer>(b, 4)); }
+
 fn std_read(b: &mut Bencher, cap: usize, read: usize) {
 	b.iter(|| {
 		let mut r = BufReader::with_capacity(cap, WORDS);
-		let mut buf = Vec::with_capacity(read);
-		unsafe { buf.set_len(read); }
+		let mut buf = vec![0; read];
 		while r.read(&mut buf[..]).unwrap() != 0 {
 			consume(buf.as_slice());
 		}
@@ -48,6 +69,8 @@ criterion_group!(benches,
 	bufref_read_vec_64x4,
 	bufref_read_mmap_4x4,
 	bufref_read_mmap_64x4,
+	bufref_read_buffered_vec_4,
+	bufref_read_buffered_mmap_4,
 	std_read_4x4,
 	std_read_64x4,
 );
@@ -1,12 +1,13 @@
-use criterion::{black_box, criterion_group, criterion_main, Bencher, Criterion};
+use criterion::{black_box, criterion_group, criterion_main, Criterion};
 
 use buf_ref_reader::*;
+use std::fs::File;
 use std::io::{Read, BufRead, BufReader, Result};
 use memchr::memchr;
 
 use std::{thread, time};
 
-static WORDS: &'static [u8] = include_bytes!("/usr/share/dict/words");
+static WORDS: &[u8] = include_bytes!("/usr/share/dict/words");
 
 struct ThrottledReader<R: Read>(R);
 impl<R: Read> Read for ThrottledReader<R> {
@@ -49,6 +50,175 @@ bufref!(throttled_bufref_read_until_vec_64,  VecBuffer,  ThrottledReader(WORDS),
 bufref!(throttled_bufref_read_until_mmap_4,  MmapBuffer, ThrottledReader(WORDS), 4096);
 bufref!(throttled_bufref_read_until_mmap_64, MmapBuffer, ThrottledReader(WORDS), 64*1024);
 
+// same as above, but tiny tails of the buffer are never read into, so it takes fewer reads
+macro_rules! bufref_min_read {
+	($fname:ident, $buf:ident, $wrapped:expr, $cap:expr, $min:expr) => {
+		fn $fname(c: &mut Criterion) {
+			c.bench_function(stringify!($fname), |b| b.iter(|| {
+				let mut r = BufRefReaderBuilder::new($wrapped)
+					.capacity($cap)
+					.min_read($min)
+					.build::<$buf>()
+					.unwrap();
+				while let Some(line) = r.read_until(b'\n').unwrap() {
+					consume(line);
+				}
+			}));
+		}
+	}
+}
+
+bufref_min_read!(throttled_bufref_read_until_min_read_vec_4,  VecBuffer, ThrottledReader(WORDS), 4096, 512);
+bufref_min_read!(throttled_bufref_read_until_min_read_vec_64, VecBuffer, ThrottledReader(WORDS), 64*1024, 512);
+
+// reading from an actual file, with the buffer memory advised one way or the other
+macro_rules! bufref_advise {
+	($fname:ident, $buf:ident, $cap:expr, $advice:expr) => {
+		fn $fname(c: &mut Criterion) {
+			c.bench_function(stringify!($fname), |b| b.iter(|| {
+				let mut r = BufRefReaderBuilder::new(File::open("/usr/share/dict/words").unwrap())
+					.capacity($cap)
+					.advise($advice)
+					.build::<$buf>()
+					.unwrap();
+				while let Some(line) = r.read_until(b'\n').unwrap() {
+					consume(line);
+				}
+			}));
+		}
+	}
+}
+
+bufref_advise!(bufref_advise_normal_mmap_64,     MmapBuffer, 64*1024, Advice::Normal);
+bufref_advise!(bufref_advise_sequential_mmap_64, MmapBuffer, 64*1024, Advice::Sequential);
+bufref_advise!(bufref_advise_willneed_mmap_64,   MmapBuffer, 64*1024, Advice::WillNeed);
+
+// same as above, but lines are copied into LineReader's own buffer
+macro_rules! bufref_line_reader {
+	($fname:ident, $buf:ident, $wrapped:expr, $cap:expr) => {
+		fn $fname(c: &mut Criterion) {
+			c.bench_function(stringify!($fname), |b| b.iter(|| {
+				let r = BufRefReaderBuilder::new($wrapped)
+					.capacity($cap)
+					.build::<$buf>()
+					.unwrap();
+				let mut lines = LineReader::new(r);
+				while let Some(line) = lines.next_line() {
+					consume(line.unwrap());
+				}
+			}));
+		}
+	}
+}
+
+bufref_line_reader!(bufref_line_reader_vec_4,   VecBuffer,  WORDS, 4096);
+bufref_line_reader!(bufref_line_reader_vec_64,  VecBuffer,  WORDS, 64*1024);
+bufref_line_reader!(bufref_line_reader_mmap_4,  MmapBuffer, WORDS, 4096);
+bufref_line_reader!(bufref_line_reader_mmap_64, MmapBuffer, WORDS, 64*1024);
+
+// same as bufref, but with naive byte-by-byte scan instead of memchr
+macro_rules! bufref_scalar {
+	($fname:ident, $buf:ident, $wrapped:expr, $cap:expr) => {
+		fn $fname(c: &mut Criterion) {
+			c.bench_function(stringify!($fname), |b| b.iter(|| {
+				let mut r = BufRefReaderBuilder::new($wrapped)
+					.capacity($cap)
+					.build::<$buf>()
+					.unwrap();
+				while let Some(line) = r.read_until_fn(|data, i| data[i] == b'\n').unwrap() {
+					consume(line);
+				}
+			}));
+		}
+	}
+}
+
+bufref_scalar!(bufref_scalar_vec_64,  VecBuffer,  WORDS, 64*1024);
+bufref_scalar!(bufref_scalar_mmap_64, MmapBuffer, WORDS, 64*1024);
+
+// same as bufref_scalar, but with lookups in ByteSet, which do not depend on the size of the set
+macro_rules! bufref_read_until_set {
+	($fname:ident, $buf:ident, $wrapped:expr, $cap:expr, $set:expr) => {
+		fn $fname(c: &mut Criterion) {
+			let set = $set;
+			c.bench_function(stringify!($fname), |b| b.iter(|| {
+				let mut r = BufRefReaderBuilder::new($wrapped)
+					.capacity($cap)
+					.build::<$buf>()
+					.unwrap();
+				while let Some(line) = r.read_until_set(&set).unwrap() {
+					consume(line);
+				}
+			}));
+		}
+	}
+}
+
+bufref_read_until_set!(bufref_read_until_set_vec_64,        VecBuffer,  WORDS, 64*1024, ByteSet::from_bytes(b"\n"));
+bufref_read_until_set!(bufref_read_until_set_mmap_64,       MmapBuffer, WORDS, 64*1024, ByteSet::from_bytes(b"\n"));
+// every byte that is not in WORDS, which yields the same lines
+bufref_read_until_set!(bufref_read_until_set_large_vec_64,  VecBuffer,  WORDS, 64*1024, ByteSet::from_fn(|c| !c.is_ascii_alphabetic() && c != b'\''));
+bufref_read_until_set!(bufref_read_until_set_large_mmap_64, MmapBuffer, WORDS, 64*1024, ByteSet::from_fn(|c| !c.is_ascii_alphabetic() && c != b'\''));
+
+// splitting by "multi-byte" delimiter, with prebuilt searcher and without
+// (delimiter is the most frequent one, to emphasize cost of building searcher for each line)
+macro_rules! bufref_read_record {
+	($fname:ident, $buf:ident, $wrapped:expr, $cap:expr) => {
+		fn $fname(c: &mut Criterion) {
+			c.bench_function(stringify!($fname), |b| b.iter(|| {
+				let mut r = BufRefReaderBuilder::new($wrapped)
+					.capacity($cap)
+					.record_delimiter(b"\n")
+					.build::<$buf>()
+					.unwrap();
+				while let Some(x) = r.read_record().unwrap() {
+					consume(x);
+				}
+			}));
+		}
+	}
+}
+macro_rules! bufref_read_until_bytes {
+	($fname:ident, $buf:ident, $wrapped:expr, $cap:expr) => {
+		fn $fname(c: &mut Criterion) {
+			c.bench_function(stringify!($fname), |b| b.iter(|| {
+				let mut r = BufRefReaderBuilder::new($wrapped)
+					.capacity($cap)
+					.build::<$buf>()
+					.unwrap();
+				while let Some(x) = r.read_until_bytes(b"\n").unwrap() {
+					consume(x);
+				}
+			}));
+		}
+	}
+}
+
+bufref_read_record!(bufref_read_record_vec_64,  VecBuffer,  WORDS, 64*1024);
+bufref_read_record!(bufref_read_record_mmap_64, MmapBuffer, WORDS, 64*1024);
+bufref_read_until_bytes!(bufref_read_until_bytes_vec_64,  VecBuffer,  WORDS, 64*1024);
+bufref_read_until_bytes!(bufref_read_until_bytes_mmap_64, MmapBuffer, WORDS, 64*1024);
+
+// counting lines without looking at each of them, compare with bufref_read_until_*
+macro_rules! bufref_count {
+	($fname:ident, $buf:ident, $wrapped:expr, $cap:expr) => {
+		fn $fname(c: &mut Criterion) {
+			c.bench_function(stringify!($fname), |b| b.iter(|| {
+				let mut r = BufRefReaderBuilder::new($wrapped)
+					.capacity($cap)
+					.build::<$buf>()
+					.unwrap();
+				black_box(r.count_until_eof(b'\n').unwrap());
+			}));
+		}
+	}
+}
+
+bufref_count!(bufref_count_vec_4,   VecBuffer,  WORDS, 4096);
+bufref_count!(bufref_count_vec_64,  VecBuffer,  WORDS, 64*1024);
+bufref_count!(bufref_count_mmap_4,  MmapBuffer, WORDS, 4096);
+bufref_count!(bufref_count_mmap_64, MmapBuffer, WORDS, 64*1024);
+
 macro_rules! std_read_until {
 	($fname:ident, $wrapped:expr, $cap:expr) => {
 		fn $fname(c: &mut Criterion) {
@@ -178,6 +348,49 @@ bufref_read_until_long!(throttled_bufref_read_until_long_vec_64,  VecBuffer,  Th
 bufref_read_until_long!(throttled_bufref_read_until_long_mmap_4,  MmapBuffer, ThrottledReader(WORDS), 4096);
 bufref_read_until_long!(throttled_bufref_read_until_long_mmap_64, MmapBuffer, ThrottledReader(WORDS), 64*1024);
 
+macro_rules! bufref_readahead_long {
+	($fname:ident, $buf:ident, $wrapped:expr, $cap:expr, $readahead:expr) => {
+		fn $fname(c: &mut Criterion) {
+			c.bench_function(stringify!($fname), |b| b.iter(|| {
+				let mut r = BufRefReaderBuilder::new($wrapped)
+					.capacity($cap)
+					.readahead($readahead)
+					.build::<$buf>()
+					.unwrap();
+				while let Some(x) = r.read_until(b'q').unwrap() {
+					consume(x);
+				}
+			}));
+		}
+	}
+}
+
+bufref_readahead_long!(bufref_readahead_long_vec_4,  VecBuffer,  WORDS, 4096, 4);
+bufref_readahead_long!(bufref_readahead_long_mmap_4, MmapBuffer, WORDS, 4096, 4);
+
+bufref_readahead_long!(throttled_bufref_readahead_long_vec_4,  VecBuffer,  ThrottledReader(WORDS), 4096, 4);
+bufref_readahead_long!(throttled_bufref_readahead_long_mmap_4, MmapBuffer, ThrottledReader(WORDS), 4096, 4);
+
+macro_rules! bufref_increment_long {
+	($fname:ident, $buf:ident, $wrapped:expr, $cap:expr, $incr:expr) => {
+		fn $fname(c: &mut Criterion) {
+			c.bench_function(stringify!($fname), |b| b.iter(|| {
+				let mut r = BufRefReaderBuilder::new($wrapped)
+					.capacity($cap)
+					.increment($incr)
+					.build::<$buf>()
+					.unwrap();
+				while let Some(x) = r.read_until(b'q').unwrap() {
+					consume(x);
+				}
+			}));
+		}
+	}
+}
+
+bufref_increment_long!(bufref_increment_long_vec_4,  VecBuffer,  WORDS, 4096, 4096);
+bufref_increment_long!(bufref_increment_long_mmap_4, MmapBuffer, WORDS, 4096, 4096);
+
 macro_rules! std_read_until_long {
 	($fname:ident, $wrapped:expr, $cap:expr) => {
 		fn $fname(c: &mut Criterion) {
@@ -209,6 +422,35 @@ criterion_group!(benches,
 	throttled_bufref_read_until_vec_64,
 	throttled_bufref_read_until_mmap_4,
 	throttled_bufref_read_until_mmap_64,
+	throttled_bufref_read_until_min_read_vec_4,
+	throttled_bufref_read_until_min_read_vec_64,
+
+	bufref_advise_normal_mmap_64,
+	bufref_advise_sequential_mmap_64,
+	bufref_advise_willneed_mmap_64,
+
+	bufref_line_reader_vec_4,
+	bufref_line_reader_vec_64,
+	bufref_line_reader_mmap_4,
+	bufref_line_reader_mmap_64,
+
+	bufref_scalar_vec_64,
+	bufref_scalar_mmap_64,
+
+	bufref_read_until_set_vec_64,
+	bufref_read_until_set_mmap_64,
+	bufref_read_until_set_large_vec_64,
+	bufref_read_until_set_large_mmap_64,
+
+	bufref_read_record_vec_64,
+	bufref_read_record_mmap_64,
+	bufref_read_until_bytes_vec_64,
+	bufref_read_until_bytes_mmap_64,
+
+	bufref_count_vec_4,
+	bufref_count_vec_64,
+	bufref_count_mmap_4,
+	bufref_count_mmap_64,
 
 	std_read_until_4,
 	std_read_until_64,
@@ -232,6 +474,15 @@ criterion_group!(benches,
 	throttled_bufref_read_until_long_mmap_4,
 	throttled_bufref_read_until_long_mmap_64,
 
+	bufref_readahead_long_vec_4,
+	bufref_readahead_long_mmap_4,
+
+	throttled_bufref_readahead_long_vec_4,
+	throttled_bufref_readahead_long_mmap_4,
+
+	bufref_increment_long_vec_4,
+	bufref_increment_long_mmap_4,
+
 	std_read_until_long_4,
 	std_read_until_long_64,
 
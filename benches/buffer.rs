@@ -16,10 +16,42 @@ fn buf_create_vec_64(c: &mut Criterion)  { c.bench_function("buf_create_vec_64",
 fn buf_create_mmap_4(c: &mut Criterion)  { c.bench_function("buf_create_mmap_4",  |b| create::<MmapBuffer>(b, 4096)); }
 fn buf_create_mmap_64(c: &mut Criterion) { c.bench_function("buf_create_mmap_64", |b| create::<MmapBuffer>(b, 64*1024)); }
 
+/*
+lots of small appends, each followed by consumption of roughly the same amount of data,
+so that data drifts all over the ring (or towards the end of VecBuffer, which then has to compact it),
+with enlarge() being called before every append, most of the times doing nothing
+*/
+fn drift<B: Buffer>(b: &mut Bencher, cap: usize)
+where
+	B::Error: std::fmt::Debug,
+{
+	let chunk = [b'x'; 13];
+	let mut buf = B::new(cap).unwrap();
+	b.iter(|| {
+		for i in 0..10_000 {
+			buf.enlarge().unwrap();
+			// appended and consumed amounts differ, but they are the same on average
+			let n = std::cmp::min(i*7 % chunk.len() + 1, buf.appendable().len());
+			buf.appendable()[..n].copy_from_slice(&chunk[..n]);
+			buf.mark_appended(n);
+			black_box(buf.consume(i % chunk.len() + 1));
+		}
+	})
+}
+fn buf_drift_vec_4(c: &mut Criterion)   { c.bench_function("buf_drift_vec_4",   |b| drift::<VecBuffer> (b, 4096)); }
+fn buf_drift_vec_64(c: &mut Criterion)  { c.bench_function("buf_drift_vec_64",  |b| drift::<VecBuffer> (b, 64*1024)); }
+fn buf_drift_mmap_4(c: &mut Criterion)  { c.bench_function("buf_drift_mmap_4",  |b| drift::<MmapBuffer>(b, 4096)); }
+fn buf_drift_mmap_64(c: &mut Criterion) { c.bench_function("buf_drift_mmap_64", |b| drift::<MmapBuffer>(b, 64*1024)); }
+
 criterion_group!(benches,
 	buf_create_vec_4,
 	buf_create_vec_64,
 	buf_create_mmap_4,
 	buf_create_mmap_64,
+
+	buf_drift_vec_4,
+	buf_drift_vec_64,
+	buf_drift_mmap_4,
+	buf_drift_mmap_64,
 );
 criterion_main!(benches);
@@ -0,0 +1,295 @@
+use vmap::{
+	Error,
+	Input,
+	MapMut,
+	Operation,
+	allocation_size,
+};
+
+/**
+Buffer that keeps its memory locked (see `mlock(2)`), so that buffered data never ends up in swap,
+e.g. for parsing key material.
+
+Memory is zeroed out before it is unlocked and given back to the OS, both when the buffer is dropped,
+and when data is moved to a larger (or smaller) allocation.
+Note that there is a limit on how much memory process is allowed to lock (`RLIMIT_MEMLOCK`),
+hence buffer that grows past that limit fails with an error.
+
+Locking is done by `vmap`, which is portable: this uses `mlock(2)` on unix,
+and `VirtualLock()` on Windows, where the limit is the minimum working set size of the process instead.
+
+This is only available with `mlock` feature enabled.
+*/
+pub struct MlockBuffer {
+	buf: MapMut,
+	// where actual data resides within the `buf`
+	start: usize,
+	end: usize,
+}
+// only show the layout: buffer contents might be huge or sensitive
+impl std::fmt::Debug for MlockBuffer {
+	fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+		f.debug_struct("MlockBuffer")
+			.field("capacity", &self.buf.len())
+			.field("start", &self.start)
+			.field("len", &(self.end - self.start))
+			.finish()
+	}
+}
+
+fn overflow() -> Error {
+	Error::input(Operation::MapAnonymous, Input::InvalidRange)
+}
+
+// mappings can only be as large as multiple of `page`, and cannot be empty
+fn mapping_size(size: usize) -> Option<usize> {
+	std::cmp::max(size, 1).checked_next_multiple_of(allocation_size())
+}
+
+fn alloc(size: usize) -> Result<MapMut, Error> {
+	let size = mapping_size(size)
+		.and_then(|size| super::checked_size(size, 1))
+		.ok_or_else(overflow)?;
+	let buf = MapMut::new(size)?;
+	// mapping is unmapped if this fails, and there's nothing to zero out yet
+	buf.lock()?;
+	Ok(buf)
+}
+
+// zero out the mapping before it is unlocked (after which it might get swapped out) and unmapped
+fn release(buf: &mut MapMut) {
+	super::wipe(buf);
+	// FIXME ignored Result: pages stay locked until unmapped, which is about to happen anyway
+	let _ = buf.unlock();
+}
+
+impl Drop for MlockBuffer {
+	fn drop(&mut self) {
+		release(&mut self.buf);
+	}
+}
+
+impl MlockBuffer {
+	// move data into a new allocation of (at least) `size` bytes
+	fn realloc(&mut self, size: usize) -> Result<(), Error> {
+		let mut new = alloc(size)?;
+		let len = self.end - self.start;
+		new[..len].copy_from_slice(&self.buf[self.start..self.end]);
+		let mut old = std::mem::replace(&mut self.buf, new);
+		release(&mut old);
+		self.start = 0;
+		self.end = len;
+		Ok(())
+	}
+}
+
+impl super::Buffer for MlockBuffer {
+	type Error = Error;
+	fn new(size: usize) -> Result<Self, Error> {
+		let buf = MlockBuffer {
+			buf: alloc(size)?,
+			start: 0, end: 0,
+		};
+		#[cfg(debug_assertions)]
+		buf.check_invariants();
+		Ok(buf)
+	}
+	// if rounding up overflows, new() is going to fail anyway
+	fn effective_capacity(cap: usize) -> usize {
+		mapping_size(cap).unwrap_or(usize::MAX)
+	}
+	// make room for new data one way or the other
+	fn enlarge(&mut self) -> Result<super::EnlargeResult, Error> {
+		let result = if self.len() == self.buf.len() {
+			// this buffer is already full, double its size
+			self.grow()?;
+			super::EnlargeResult::Reallocated
+		} else if self.end == self.buf.len() {
+			// move data to the beginning of the buffer
+			self.buf.copy_within(self.start..self.end, 0);
+			self.end -= self.start;
+			self.start = 0;
+			super::EnlargeResult::Compacted
+		} else {
+			// there's still some room in `appendable()`, nothing to do
+			super::EnlargeResult::NoOp
+		};
+		#[cfg(debug_assertions)]
+		self.check_invariants();
+		Ok(result)
+	}
+	fn grow(&mut self) -> Result<(), Error> {
+		let newsize = super::checked_size(self.buf.len(), 2).ok_or_else(overflow)?;
+		self.realloc(newsize)?;
+		#[cfg(debug_assertions)]
+		self.check_invariants();
+		Ok(())
+	}
+	fn reserve(&mut self, additional: usize) -> Result<(), Error> {
+		if self.buf.len() - self.end < additional {
+			if self.buf.len() - self.len() >= additional {
+				// move data to the beginning of the buffer
+				self.buf.copy_within(self.start..self.end, 0);
+				self.end -= self.start;
+				self.start = 0;
+			} else {
+				let newsize = self.len().checked_add(additional).ok_or_else(overflow)?;
+				self.realloc(newsize)?;
+			}
+		}
+		#[cfg(debug_assertions)]
+		self.check_invariants();
+		Ok(())
+	}
+	fn len(&self) -> usize {
+		self.end - self.start
+	}
+	fn capacity(&self) -> usize {
+		self.buf.len()
+	}
+	fn shrink(&mut self, cap: usize) -> Result<(), Error> {
+		if self.is_empty() && Self::effective_capacity(cap) < self.buf.len() {
+			self.realloc(cap)?;
+		}
+		#[cfg(debug_assertions)]
+		self.check_invariants();
+		Ok(())
+	}
+	fn filled(&self) -> &[u8] {
+		&self.buf[ self.start .. self.end ]
+	}
+	// consumed data is only overwritten by compaction, which also resets `start`
+	fn retained(&self) -> &[u8] {
+		&self.buf[ .. self.end ]
+	}
+	fn appendable(&mut self) -> &mut [u8] {
+		&mut self.buf[ self.end .. ]
+	}
+	fn mark_appended(&mut self, amount: usize) {
+		self.end += amount;
+		#[cfg(debug_assertions)]
+		self.check_invariants();
+	}
+	fn scratch(&mut self) -> &mut [u8] {
+		assert!(self.is_empty(), "cannot use buffer as scratch space, it still holds {} bytes", self.len());
+		self.start = 0;
+		self.end = 0;
+		&mut self.buf[..]
+	}
+	fn consume(&mut self, amount: usize) -> &[u8] {
+		self.consume_and_peek(amount, 0)
+	}
+	fn consume_mut(&mut self, amount: usize) -> &mut [u8] {
+		let amount = std::cmp::min(amount, self.len());
+		let start = self.start;
+		self.start += amount;
+		#[cfg(debug_assertions)]
+		self.check_invariants();
+		&mut self.buf[ start .. (start+amount) ]
+	}
+	fn unconsume(&mut self, amount: usize) {
+		assert!(amount <= self.start, "cannot unconsume {} bytes, only {} are retained", amount, self.start);
+		self.start -= amount;
+		#[cfg(debug_assertions)]
+		self.check_invariants();
+	}
+	fn consume_and_peek(&mut self, amount: usize, peek: usize) -> &[u8] {
+		let amount = std::cmp::min(amount, self.len());
+		let peek = std::cmp::min(peek, self.len() - amount);
+		let start = self.start;
+		self.start += amount;
+		#[cfg(debug_assertions)]
+		self.check_invariants();
+		&self.buf[ start .. (start+amount+peek) ]
+	}
+	fn kind(&self) -> super::BufferKind {
+		super::BufferKind::Mlock
+	}
+	// see release()
+	fn enable_zero_on_drop(&mut self) -> bool {
+		true
+	}
+	#[cfg(any(test, feature = "debug-internals"))]
+	fn layout(&self) -> super::BufferLayout {
+		super::BufferLayout {
+			capacity: self.buf.len(),
+			start: self.start,
+			len: self.end - self.start,
+			appendable_len: self.buf.len() - self.end,
+		}
+	}
+	#[cfg(debug_assertions)]
+	fn check_invariants(&self) {
+		assert!(self.start <= self.end, "start {} is past end {}", self.start, self.end);
+		assert!(self.end <= self.buf.len(), "end {} is past capacity {}", self.end, self.buf.len());
+	}
+}
+
+impl super::CloneableBuffer for MlockBuffer {
+	fn try_clone(&self) -> Result<Self, Error> {
+		let mut buf = alloc(self.buf.len())?;
+		buf.copy_from_slice(&self.buf);
+		Ok(MlockBuffer {
+			buf,
+			start: self.start,
+			end: self.end,
+		})
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+	use crate::buffer::Buffer;
+
+	#[test]
+	fn enlarge() {
+		let page = allocation_size();
+		let mut buf = MlockBuffer::new(1).unwrap();
+		assert_eq!(buf.capacity(), page);
+
+		buf.mark_appended(page);
+		buf.consume(page/2);
+		assert_eq!(buf.enlarge().unwrap(), crate::buffer::EnlargeResult::Compacted);
+		assert_eq!(buf.capacity(), page);
+		assert_eq!(buf.appendable().len(), page/2);
+
+		buf.append_from(&vec![b'x'; page]).unwrap();
+		// reallocated
+		assert_eq!(buf.capacity(), page*2);
+		assert_eq!(buf.len(), page + page/2);
+
+		buf.consume(buf.len());
+		buf.shrink(1).unwrap();
+		assert_eq!(buf.capacity(), page);
+	}
+
+	#[test]
+	fn release() {
+		let mut buf = MlockBuffer::new(16).unwrap();
+		buf.append_from(b"correct horse battery staple").unwrap();
+		buf.consume(8);
+		// this is what happens on drop
+		super::release(&mut buf.buf);
+		assert!(buf.buf.iter().all(|&c| c == 0));
+	}
+
+	// amount of memory this process has locked, in KiB
+	#[cfg(target_os = "linux")]
+	fn locked() -> usize {
+		let status = std::fs::read_to_string("/proc/self/status").unwrap();
+		let line = status.lines().find(|line| line.starts_with("VmLck:")).unwrap();
+		line.split_whitespace().nth(1).unwrap().parse().unwrap()
+	}
+
+	#[cfg(target_os = "linux")]
+	#[test]
+	fn locked_memory() {
+		// a single page fits into any RLIMIT_MEMLOCK that allows locking at all (containers often default to 64 KiB)
+		let page = allocation_size();
+		let buf = MlockBuffer::new(page).unwrap();
+		// other tests might lock (and unlock) their own buffers in the meantime, but never this one
+		assert!(locked() >= page / 1024);
+		drop(buf);
+	}
+}
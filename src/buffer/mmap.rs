@@ -1,20 +1,45 @@
+use std::ptr::{read_volatile, write_volatile};
 use std::slice::from_raw_parts_mut;
 use std::slice::SliceIndex;
 use vmap::os::{
+	advise,
 	map_ring,
 	unmap_ring,
 };
 use vmap::{
+	Advise,
 	Error,
+	Input,
+	Operation,
 	allocation_size,
 };
 
+/*
+Both halves of the mirrored mapping are owned exclusively by the Ring:
+the mapping is created in `new()`, is never shared or aliased outside of this struct,
+and is only released in `drop()`.
+Hence it's as safe to move the Ring to (or share `&Ring` with) another thread
+as it is to do so with `&mut [u8]` that it holds, and it gets `Send` and `Sync` automatically, no `unsafe impl` needed.
+(If `buf` is ever turned into a raw pointer, it will need explicit `unsafe impl Send` and `unsafe impl Sync` for the same reasons.)
+*/
 struct Ring<'a> {
 	buf: &'a mut [u8],
 }
 impl<'a> Ring<'a> {
 	fn new(size: usize) -> Result<Self, Error> {
+		// both halves have to fit into the address space
+		super::checked_size(size, 2).ok_or_else(overflow)?;
 		let buf = map_ring(size)?;
+		// everything else relies on the second half actually mirroring the first one,
+		// so make sure that's the case before handing out any slices of it
+		if buf.is_null() || !unsafe { mirrored(buf, size) } {
+			if !buf.is_null() {
+				unsafe {
+					let _ = unmap_ring(buf, size);
+				}
+			}
+			return Err(Error::input(Operation::RingSecondary, Input::InvalidRange));
+		}
 		let buf = unsafe { from_raw_parts_mut(buf, size*2) };
 		Ok(Ring { buf })
 	}
@@ -22,7 +47,50 @@ impl<'a> Ring<'a> {
 		// underlying slice is twice as long
 		self.buf.len()/2
 	}
+	// both halves are advised, as data is accessed through either of them
+	fn advise(&mut self, advice: super::Advice) -> Result<(), Error> {
+		let advice = match advice {
+			super::Advice::Normal => Advise::Normal,
+			super::Advice::Sequential => Advise::Sequential,
+			super::Advice::Random => Advise::Random,
+			super::Advice::WillNeed => Advise::WillNeed,
+		};
+		unsafe { advise(self.buf.as_mut_ptr(), self.buf.len(), advice) }
+	}
+}
+/*
+check that bytes written to either half of the mapping at both of its ends show up in the other half
+
+volatile access keeps compiler from assuming that writes to one half cannot affect the other
+*/
+unsafe fn mirrored(buf: *mut u8, size: usize) -> bool {
+	if size == 0 {
+		// nothing to mirror
+		return true;
+	}
+	for &(from, to) in &[(0, size), (size, 0), (size-1, size*2-1), (size*2-1, size-1)] {
+		for &sentinel in &[0xa5, 0x5a] {
+			write_volatile(buf.add(from), sentinel);
+			if read_volatile(buf.add(to)) != sentinel {
+				return false;
+			}
+		}
+	}
+	// mapping is expected to be zeroed, just like any fresh one
+	write_volatile(buf, 0);
+	write_volatile(buf.add(size-1), 0);
+	true
+}
+
+fn overflow() -> Error {
+	Error::input(Operation::RingAllocate, Input::InvalidRange)
 }
+
+// mappings can only be as large as multiple of `page`
+fn round_up(size: usize, page: usize) -> Option<usize> {
+	size.checked_next_multiple_of(page)
+}
+
 impl<'a> Drop for Ring<'a> {
 	fn drop(&mut self) {
 		unsafe {
@@ -43,44 +111,146 @@ impl<'a, I: SliceIndex<[u8]>> std::ops::IndexMut<I> for Ring<'a> {
 	}
 }
 
-/// Buffer that uses circular buffer implemented with mirrored memory maps
+/**
+Buffer that uses circular buffer implemented with mirrored memory maps
+
+This buffer owns its mapping exclusively, hence it is both `Send` and `Sync`,
+and so is [`BufRefReader`](struct.BufRefReader.html) that uses it (as long as the underlying reader is).
+*/
 pub struct MmapBuffer<'a> {
 	buf: Ring<'a>,
 	// position of data within the `buf`
 	start: usize,
 	len: usize,
+	// how many bytes right before `start` still hold consumed data
+	discarded: usize,
+	// applied to every new mapping
+	advice: super::Advice,
+	// see Buffer::enable_zero_on_drop()
+	zero_on_drop: bool,
+}
+// only show the layout: buffer contents might be huge or sensitive
+impl std::fmt::Debug for MmapBuffer<'_> {
+	fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+		f.debug_struct("MmapBuffer")
+			.field("capacity", &self.buf.capacity())
+			.field("start", &self.start)
+			.field("len", &self.len)
+			.finish()
+	}
+}
+
+impl<'a> MmapBuffer<'a> {
+	// new mapping with the same access pattern as the current one
+	fn ring(&self, size: usize) -> Result<Ring<'a>, Error> {
+		let mut ring = Ring::new(size)?;
+		if self.advice != super::Advice::Normal {
+			ring.advise(self.advice)?;
+		}
+		Ok(ring)
+	}
+	// old mapping is wiped before it is unmapped, if asked to
+	fn replace(&mut self, new: Ring<'a>) {
+		self.wipe();
+		self.buf = new;
+	}
+	fn wipe(&mut self) {
+		if self.zero_on_drop {
+			// second half is the same memory
+			let cap = self.buf.capacity();
+			super::wipe(&mut self.buf[..cap]);
+		}
+	}
 }
+
+impl Drop for MmapBuffer<'_> {
+	fn drop(&mut self) {
+		self.wipe();
+	}
+}
+
 impl<'a> super::Buffer for MmapBuffer<'a> {
 	type Error = Error;
 	fn new(size: usize) -> Result<Self, Error> {
-		let size = size.next_multiple_of(allocation_size());
+		let size = round_up(size, allocation_size()).ok_or_else(overflow)?;
 		let buf = Ring::new(size)?;
-		Ok(MmapBuffer {
+		let buf = MmapBuffer {
 			buf,
 			start: 0, len: 0,
-		})
+			discarded: 0,
+			advice: super::Advice::Normal,
+			zero_on_drop: false,
+		};
+		#[cfg(debug_assertions)]
+		buf.check_invariants();
+		Ok(buf)
+	}
+	// if rounding up overflows, new() is going to fail anyway
+	fn effective_capacity(cap: usize) -> usize {
+		round_up(cap, allocation_size()).unwrap_or(usize::MAX)
 	}
 	fn filled(&self) -> &[u8] {
 		&self.buf[ self.start .. (self.start + self.len) ]
 	}
+	/*
+	consumed data might begin in the second half of the ring:
+	|  b--c | a--b  c|
+	|-c  a--|-c  a--b|
+	in this case, view the same data through the mirror instead,
+	which does not go past the end of it as `discarded + len` never exceeds the capacity
+	*/
+	fn retained(&self) -> &[u8] {
+		let start = if self.discarded <= self.start {
+			self.start
+		} else {
+			self.start + self.buf.capacity()
+		};
+		&self.buf[ (start - self.discarded) .. (start + self.len) ]
+	}
 	// make room for new data one way or the other
-	fn enlarge(&mut self) -> Result<(), Error> {
-		let bufsize = self.buf.capacity();
-		if self.len == bufsize {
-			/*
-			we used to have configurable increments for the bufsize
-			now though we double buffer size, just like rust's vec/raw_vec do
-			*/
-			let newsize = bufsize * 2;
-			let mut new = Ring::new(newsize)?;
-			// move data at the start of new buffer
-			new[..bufsize].copy_from_slice(&self.buf[self.start..(self.start+self.len)]);
-			self.start = 0;
-			self.buf = new;
+	fn enlarge(&mut self) -> Result<super::EnlargeResult, Error> {
+		let result = if self.len == self.buf.capacity() {
+			self.grow()?;
+			super::EnlargeResult::Reallocated
 		} else {
 			// there's plenty of room in the buffer,
 			// nothing to do here
+			super::EnlargeResult::NoOp
+		};
+		#[cfg(debug_assertions)]
+		self.check_invariants();
+		Ok(result)
+	}
+	fn grow(&mut self) -> Result<(), Error> {
+		let bufsize = self.buf.capacity();
+		// double buffer size, just like rust's vec/raw_vec do
+		// (additive growth, see BufRefReaderBuilder::increment(), goes through reserve() instead)
+		let newsize = super::checked_size(bufsize, 2).ok_or_else(overflow)?;
+		let mut new = self.ring(newsize)?;
+		// move data at the start of new buffer
+		new[..self.len].copy_from_slice(&self.buf[self.start..(self.start+self.len)]);
+		self.start = 0;
+		self.discarded = 0;
+		self.replace(new);
+		#[cfg(debug_assertions)]
+		self.check_invariants();
+		Ok(())
+	}
+	// ring has no need to move data around to make room, it can only be reallocated
+	fn reserve(&mut self, additional: usize) -> Result<(), Error> {
+		let bufsize = self.buf.capacity();
+		if bufsize - self.len < additional {
+			let newsize = self.len.checked_add(additional)
+				.and_then(|size| round_up(size, allocation_size()))
+				.ok_or_else(overflow)?;
+			let mut new = self.ring(newsize)?;
+			new[..self.len].copy_from_slice(&self.buf[self.start..(self.start+self.len)]);
+			self.start = 0;
+			self.discarded = 0;
+			self.replace(new);
 		}
+		#[cfg(debug_assertions)]
+		self.check_invariants();
 		Ok(())
 	}
 	/*
@@ -89,12 +259,27 @@ impl<'a> super::Buffer for MmapBuffer<'a> {
 	|-b  a-|-b  a-|
 	*/
 	fn appendable(&mut self) -> &mut [u8] {
+		// free space of the ring is where consumed data resides
+		self.discarded = 0;
+		#[cfg(debug_assertions)]
+		self.check_invariants();
 		let end = self.start + self.len;
 		let remaining = self.buf.capacity() - self.len;
 		&mut self.buf[ end .. (end+remaining) ]
 	}
 	fn mark_appended(&mut self, amount: usize) {
 		self.len += amount;
+		self.discarded = 0;
+		#[cfg(debug_assertions)]
+		self.check_invariants();
+	}
+	// same as appendable(), except that it's not wrapped around the end of the ring
+	fn scratch(&mut self) -> &mut [u8] {
+		assert!(self.is_empty(), "cannot use buffer as scratch space, it still holds {} bytes", self.len());
+		self.start = 0;
+		self.discarded = 0;
+		let cap = self.buf.capacity();
+		&mut self.buf[..cap]
 	}
 	/*
 	returns reference to first half of the buffer
@@ -112,17 +297,149 @@ impl<'a> super::Buffer for MmapBuffer<'a> {
 			self.start -= self.buf.capacity();
 		}
 		self.len -= amount;
+		self.discarded += amount;
+		#[cfg(debug_assertions)]
+		self.check_invariants();
 		&self.buf[ start .. (start+amount) ]
 	}
+	fn consume_mut(&mut self, amount: usize) -> &mut [u8] {
+		let start = self.start;
+		let amount = std::cmp::min(amount, self.len());
+
+		self.start += amount;
+		if self.start >= self.buf.capacity() {
+			// keep self.start within bufsize
+			self.start -= self.buf.capacity();
+		}
+		self.len -= amount;
+		self.discarded += amount;
+		#[cfg(debug_assertions)]
+		self.check_invariants();
+		&mut self.buf[ start .. (start+amount) ]
+	}
+	/*
+	same as above, but returned slice also covers (up to) `peek` bytes of data that is kept,
+	which is fine as start+len never goes past the mirrored half of the buffer
+	*/
+	fn consume_and_peek(&mut self, amount: usize, peek: usize) -> &[u8] {
+		let start = self.start;
+		let amount = std::cmp::min(amount, self.len());
+		let peek = std::cmp::min(peek, self.len() - amount);
+
+		self.start += amount;
+		if self.start >= self.buf.capacity() {
+			// keep self.start within bufsize
+			self.start -= self.buf.capacity();
+		}
+		self.len -= amount;
+		self.discarded += amount;
+		#[cfg(debug_assertions)]
+		self.check_invariants();
+		&self.buf[ start .. (start+amount+peek) ]
+	}
+	// retained() has already shown that consumed data is intact both before and after the end of the ring
+	fn unconsume(&mut self, amount: usize) {
+		assert!(amount <= self.discarded, "cannot unconsume {} bytes, only {} are retained", amount, self.discarded);
+		if amount > self.start {
+			// keep self.start within bufsize
+			self.start += self.buf.capacity();
+		}
+		self.start -= amount;
+		self.len += amount;
+		self.discarded -= amount;
+		#[cfg(debug_assertions)]
+		self.check_invariants();
+	}
 	fn len(&self) -> usize {
 		self.len
 	}
+	fn capacity(&self) -> usize {
+		self.buf.capacity()
+	}
+	fn shrink(&mut self, cap: usize) -> Result<(), Error> {
+		// if rounding up overflows, it's not going to be any smaller than current capacity anyway
+		let cap = round_up(cap, allocation_size()).unwrap_or(usize::MAX);
+		if self.len == 0 && cap < self.buf.capacity() {
+			let new = self.ring(cap)?;
+			self.replace(new);
+			self.start = 0;
+			self.discarded = 0;
+		}
+		#[cfg(debug_assertions)]
+		self.check_invariants();
+		Ok(())
+	}
+	fn kind(&self) -> super::BufferKind {
+		super::BufferKind::MmapRing
+	}
+	fn advise(&mut self, advice: super::Advice) -> Result<(), Error> {
+		self.buf.advise(advice)?;
+		self.advice = advice;
+		Ok(())
+	}
+	fn enable_zero_on_drop(&mut self) -> bool {
+		self.zero_on_drop = true;
+		true
+	}
+	#[cfg(any(test, feature = "debug-internals"))]
+	fn layout(&self) -> super::BufferLayout {
+		super::BufferLayout {
+			capacity: self.buf.capacity(),
+			start: self.start,
+			len: self.len,
+			appendable_len: self.buf.capacity() - self.len,
+		}
+	}
+	#[cfg(debug_assertions)]
+	fn check_invariants(&self) {
+		let cap = self.buf.capacity();
+		assert!(self.start < cap, "start {} is past capacity {}", self.start, cap);
+		assert!(self.len <= cap, "len {} is past capacity {}", self.len, cap);
+		assert!(self.discarded + self.len <= cap, "discarded {} and len {} do not fit into capacity {}", self.discarded, self.len, cap);
+	}
 }
 
+// second half of the new ring mirrors the first one, so copying the latter is enough
+impl<'a> super::CloneableBuffer for MmapBuffer<'a> {
+	fn try_clone(&self) -> Result<Self, Error> {
+		let cap = self.buf.capacity();
+		let mut buf = self.ring(cap)?;
+		buf[..cap].copy_from_slice(&self.buf[..cap]);
+		Ok(MmapBuffer {
+			buf,
+			start: self.start,
+			len: self.len,
+			discarded: self.discarded,
+			advice: self.advice,
+			zero_on_drop: self.zero_on_drop,
+		})
+	}
+}
+
+#[cfg(test)]
 mod tests {
 	use super::*;
 	use crate::buffer::Buffer;
 
+	#[test]
+	fn mirror() {
+		for &pages in &[1, 2, 16] {
+			let size = pages * allocation_size();
+			let mut ring = Ring::new(size).unwrap();
+			assert_eq!(ring.capacity(), size);
+			for i in (0..size).step_by(1021) {
+				ring[i] = (i % 251) as u8;
+				assert_eq!(ring[i + size], (i % 251) as u8);
+				ring[i + size] = !(i % 251) as u8;
+				assert_eq!(ring[i], !(i % 251) as u8);
+			}
+			// mirror check leaves no traces
+			assert_eq!(ring[size-1], 0);
+		}
+		// whatever vmap has to say about empty mappings, it should not end up reading past them
+		let _ = Ring::new(0);
+	}
+
 	#[test]
 	fn enlarge() {
 		let mut buf = MmapBuffer::new(4096).unwrap();
@@ -135,7 +452,7 @@ mod tests {
 		assert_eq!(buf.appendable().len(), 4096-1024);
 
 		// buffer still has space, should be noop
-		buf.enlarge().unwrap();
+		assert_eq!(buf.enlarge().unwrap(), crate::buffer::EnlargeResult::NoOp);
 		assert_eq!(buf.appendable().len(), 4096-1024);
 
 		buf.mark_appended(4096-1024);
@@ -152,7 +469,153 @@ mod tests {
 		assert_eq!(buf.appendable().len(), 0);
 
 		// we have no space left, this should cause reallocation with doubling of the initial capacity
+		assert_eq!(buf.enlarge().unwrap(), crate::buffer::EnlargeResult::Reallocated);
+		assert_eq!(buf.appendable().len(), 4096);
+	}
+
+	#[test]
+	fn grow() {
+		let mut buf = MmapBuffer::new(4096).unwrap();
+		buf.mark_appended(2048);
+		buf.consume(1024);
+		buf.grow().unwrap();
+		assert_eq!(buf.capacity(), 8192);
+		assert_eq!(buf.len(), 1024);
+		assert_eq!(buf.appendable().len(), 7168);
+	}
+
+	#[test]
+	fn invariants() {
+		// every step below is checked by check_invariants()
+		let mut buf = MmapBuffer::new(4096).unwrap();
+		for i in 0..10000 {
+			buf.enlarge().unwrap();
+			let n = std::cmp::min(i % 1000, buf.appendable().len());
+			buf.mark_appended(n);
+			buf.consume(i % 500);
+			buf.consume_and_peek(i % 300, i % 2);
+		}
+		buf.consume(buf.len());
+		buf.shrink(4096).unwrap();
+		assert_eq!(buf.capacity(), 4096);
+	}
+
+	#[test]
+	#[cfg(debug_assertions)]
+	#[should_panic(expected = "is past capacity")]
+	fn invariants_violated() {
+		let mut buf = MmapBuffer::new(4096).unwrap();
+		// appending more than there's room for
+		buf.mark_appended(4097);
+	}
+
+	#[test]
+	fn retained() {
+		let mut buf = MmapBuffer::new(4096).unwrap();
+		let data: Vec<u8> = (0..8192).map(|i| (i % 251) as u8).collect();
+
+		buf.appendable().copy_from_slice(&data[..4096]);
+		buf.mark_appended(4096);
+		buf.consume(3000);
+		assert_eq!(buf.retained(), &data[..4096]);
+
+		// appending new data clobbers consumed bytes
+		buf.appendable()[..2000].copy_from_slice(&data[4096..6096]);
+		buf.mark_appended(2000);
+		assert_eq!(buf.retained(), &data[3000..6096]);
+
+		// consumed data now wraps around the end of the ring
+		let chunk = buf.consume(2000);
+		assert_eq!(chunk, &data[3000..5000]);
+		assert_eq!(buf.retained(), &data[3000..6096]);
+		assert_eq!(buf.filled(), &data[5000..6096]);
+	}
+
+	#[test]
+	fn effective_capacity() {
+		// whatever the actual page size is
+		for page in &[4096, 16384, 65536] {
+			assert_eq!(round_up(0, *page), Some(0));
+			assert_eq!(round_up(1, *page), Some(*page));
+			assert_eq!(round_up(*page, *page), Some(*page));
+			assert_eq!(round_up(*page + 1, *page), Some(*page * 2));
+			assert_eq!(round_up(usize::MAX - 1, *page), None);
+		}
+
+		let page = allocation_size();
+		for cap in &[1, page, page + 1, page * 3 - 1] {
+			let buf = MmapBuffer::new(*cap).unwrap();
+			assert_eq!(MmapBuffer::effective_capacity(*cap), buf.capacity());
+		}
+		assert_eq!(MmapBuffer::effective_capacity(page + 1), page * 2);
+	}
+
+	#[test]
+	fn layout() {
+		let layout = |capacity, start, len, appendable_len| crate::buffer::BufferLayout { capacity, start, len, appendable_len };
+		let mut buf = MmapBuffer::new(4096).unwrap();
+		assert_eq!(buf.layout(), layout(4096, 0, 0, 4096));
+		buf.mark_appended(3000);
+		buf.consume(2000);
+		assert_eq!(buf.layout(), layout(4096, 2000, 1000, 3096));
+		buf.mark_appended(3096);
+		buf.consume(2500);
+		// start drifts past the end of the ring
+		assert_eq!(buf.layout(), layout(4096, 404, 1596, 2500));
+		buf.mark_appended(2500);
 		buf.enlarge().unwrap();
+		assert_eq!(buf.layout(), layout(8192, 0, 4096, 4096));
+	}
+
+	#[test]
+	fn shrink() {
+		let mut buf = MmapBuffer::new(4096).unwrap();
+		buf.mark_appended(4096);
+		buf.enlarge().unwrap();
+		assert_eq!(buf.capacity(), 8192);
+
+		// buffer still holds data, should be noop
+		buf.shrink(4096).unwrap();
+		assert_eq!(buf.capacity(), 8192);
+
+		buf.consume(4096);
+		buf.shrink(4096).unwrap();
+		assert_eq!(buf.capacity(), 4096);
 		assert_eq!(buf.appendable().len(), 4096);
 	}
+
+	#[test]
+	fn advise() {
+		use crate::buffer::{Advice, CloneableBuffer};
+		let mut buf = MmapBuffer::new(4096).unwrap();
+		buf.advise(Advice::Sequential).unwrap();
+		buf.appendable()[..3].copy_from_slice(b"abc");
+		buf.mark_appended(4096);
+		// new mapping is advised just the same
+		buf.enlarge().unwrap();
+		assert_eq!(buf.advice, Advice::Sequential);
+		assert_eq!(&buf.filled()[..3], b"abc");
+		assert_eq!(buf.try_clone().unwrap().advice, Advice::Sequential);
+		buf.advise(Advice::Normal).unwrap();
+		assert_eq!(buf.advice, Advice::Normal);
+	}
+
+	#[test]
+	fn zero_on_drop() {
+		let mut buf = MmapBuffer::new(4096).unwrap();
+		// not asked to
+		buf.appendable()[..3].copy_from_slice(b"abc");
+		buf.wipe();
+		assert_eq!(&buf.appendable()[..3], b"abc");
+
+		assert!(buf.enable_zero_on_drop());
+		buf.mark_appended(4096);
+		// data survives reallocation
+		buf.grow().unwrap();
+		assert_eq!(&buf.filled()[..3], b"abc");
+		buf.appendable()[..3].copy_from_slice(b"def");
+		buf.mark_appended(3);
+		buf.wipe();
+		assert!(buf.buf[..].iter().all(|&c| c == 0));
+	}
 }
@@ -50,35 +50,356 @@ where Self: std::marker::Sized
 	/// Allocate new buffer of at least size `cap`, or more.
 	fn new(cap: usize) -> Result<Self, Self::Error>;
 	/**
+	Allocate new buffer of size `cap`, as close to it as backend allows.
+
+	[`VecBuffer`](struct.VecBuffer.html) honors `cap` exactly,
+	while [`MmapBuffer`](struct.MmapBuffer.html) still has to round it up to the page size.
+	By default, this is the same as [`new()`](#tymethod.new).
+	*/
+	fn new_exact(cap: usize) -> Result<Self, Self::Error> {
+		Self::new(cap)
+	}
+	/**
+	Capacity of the buffer that [`new(cap)`](#tymethod.new) would allocate, e.g. after rounding `cap` up to the page size.
+
+	By default, this is `cap` itself.
+	*/
+	fn effective_capacity(cap: usize) -> usize {
+		cap
+	}
+	/**
 	Part of the buffer next to the [`filled()`](#tymethod.filled) that can be used to append data.
 
 	Use [`mark_appended()`](#tymethod.mark_appended) to actually append data written to this slice.
 	*/
 	fn appendable(&mut self) -> &mut [u8];
+	/**
+	Same as [`appendable()`](#tymethod.appendable), but as a slice of possibly uninitialized bytes,
+	e.g. for sources that fill `MaybeUninit` buffers.
+
+	By default, this is `appendable()` itself: all the buffers in this crate keep their memory initialized.
+
+	# Safety
+
+	Caller must not write uninitialized values into returned slice
+	(i.e. must not de-initialize bytes that are already initialized),
+	as these bytes are still going to be handed out as `&[u8]` by `appendable()` and, once appended, by [`filled()`](#tymethod.filled).
+	*/
+	unsafe fn appendable_uninit(&mut self) -> &mut [std::mem::MaybeUninit<u8>] {
+		let appendable = self.appendable();
+		// MaybeUninit<u8> has the same layout as u8
+		std::slice::from_raw_parts_mut(appendable.as_mut_ptr() as *mut std::mem::MaybeUninit<u8>, appendable.len())
+	}
 	/// Attaches `amount` bytes of [`appendable()`](#tymethod.appendable)
 	/// to [`filled()`](#tymethod.filled) part of the buffer
 	fn mark_appended(&mut self, amount: usize);
 	/**
+	Copies `data` into [`appendable()`](#tymethod.appendable) part of the buffer and [attaches](#tymethod.mark_appended) it,
+	[enlarging](#tymethod.enlarge) the buffer as many times as needed to fit all of it.
+
+	This is for seeding the buffer with data that is already at hand, e.g. left over from another reader.
+	Panics if `enlarge()` does not make any room in the full buffer.
+	*/
+	fn append_from(&mut self, mut data: &[u8]) -> Result<(), Self::Error> {
+		while !data.is_empty() {
+			// no-op while there's some room left
+			self.enlarge()?;
+			let appendable = self.appendable();
+			let len = std::cmp::min(appendable.len(), data.len());
+			assert!(len > 0, "buffer cannot be enlarged");
+			appendable[..len].copy_from_slice(&data[..len]);
+			self.mark_appended(len);
+			data = &data[len..];
+		}
+		Ok(())
+	}
+	/**
+	Whole capacity of the empty buffer as a single writable region, e.g. for a custom fill strategy that reads a known amount of data at once.
+
+	This is only valid when the buffer is [empty](#method.is_empty), and panics otherwise.
+	Whatever is [retained](#tymethod.retained) is discarded, and written data is attached with [`mark_appended()`](#tymethod.mark_appended),
+	just like with [`appendable()`](#tymethod.appendable).
+	For ring buffers, this is the first half of the ring.
+	*/
+	fn scratch(&mut self) -> &mut [u8];
+	/**
 	Split [`filled()`](#tymethod.filled) part of the buffer,
 	returning up to `amount` bytes from the beginning while also marking them as discarded
 	right after lifetime of returned slice ends (i.e. before another call to any of `Buffer`'s methods that accepts `&mut self`).
 	*/
 	fn consume(&mut self, amount: usize) -> &[u8];
 	/**
+	Like [`consume()`](#tymethod.consume), but returned slice is also followed by up to `peek` bytes
+	that are *not* marked as discarded and remain in [`filled()`](#tymethod.filled) part of the buffer.
+	*/
+	fn consume_and_peek(&mut self, amount: usize, peek: usize) -> &[u8];
+	/**
+	Same as [`consume()`](#tymethod.consume), but returned slice is mutable.
+
+	This is meant for in-place editing of data that is about to be discarded anyway.
+	Changes are still visible in [`retained()`](#tymethod.retained) for as long as consumed data remains intact there.
+	*/
+	fn consume_mut(&mut self, amount: usize) -> &mut [u8];
+	/**
+	Undo consumption of the last `amount` bytes, putting them back at the beginning of the [`filled()`](#tymethod.filled) part of the buffer.
+
+	Only bytes that are still [retained](#tymethod.retained) can be put back, and this panics if there's less than `amount` of them.
+	*/
+	fn unconsume(&mut self, amount: usize);
+	/**
 	Grow [`appendable()`](#tymethod.appendable) part of the buffer one way or the other
 	(by e.g. reallocating filled part of the buffer, or reallocating buffer itself)
 
 	Does nothing if `appendable()` has some capacity left.
+	Returns which of these actually happened, see [`EnlargeResult`](enum.EnlargeResult.html).
+	*/
+	fn enlarge(&mut self) -> Result<EnlargeResult, Self::Error>;
+	/**
+	Grow [`appendable()`](#tymethod.appendable) part of the buffer by reallocating the buffer itself (e.g. doubling its capacity),
+	even if `appendable()` has some capacity left.
+
+	By default, this is the same as [`enlarge()`](#tymethod.enlarge).
+	*/
+	fn grow(&mut self) -> Result<(), Self::Error> {
+		self.enlarge().map(|_| ())
+	}
+	/**
+	Make sure [`appendable()`](#tymethod.appendable) part of the buffer is at least `additional` bytes long,
+	by moving data that is already in the buffer (like [`enlarge()`](#tymethod.enlarge) does) if that's enough,
+	or by reallocating the buffer to fit exactly that much more data (or more, if backend needs to round it up).
+
+	By default, this calls [`grow()`](#method.grow) until there's enough room, or until it stops making any.
 	*/
-	fn enlarge(&mut self) -> Result<(), Self::Error>;
+	fn reserve(&mut self, additional: usize) -> Result<(), Self::Error> {
+		while self.appendable().len() < additional {
+			let cap = self.capacity();
+			self.grow()?;
+			if self.capacity() == cap {
+				break;
+			}
+		}
+		Ok(())
+	}
 	/// Return filled part of the buffer
 	fn filled(&self) -> &[u8];
 	/**
+	Return [`filled()`](#tymethod.filled) part of the buffer,
+	preceded by as many of the most recently [consumed](#tymethod.consume) bytes as are still intact.
+
+	How long consumed data survives depends on the implementation,
+	but generally it is lost as soon as the buffer is compacted, reallocated, or its free space is handed out via [`appendable()`](#tymethod.appendable).
+	*/
+	fn retained(&self) -> &[u8];
+	/**
 	Size of [`filled()`](#tymethod.filled) part of the buffer
 
 	This is generally faster (and a bit more readable) than equivalent call to `.filled().len()`.
 	*/
 	fn len(&self) -> usize;
+	/// Returns `true` if [`filled()`](#tymethod.filled) part of the buffer is empty
+	fn is_empty(&self) -> bool {
+		self.len() == 0
+	}
+	/// Total size of the buffer, i.e. the most data it can hold without calling [`enlarge()`](#tymethod.enlarge)
+	fn capacity(&self) -> usize;
+	/**
+	Reallocate the buffer so that its capacity gets down to `cap` (or more, if backend needs to round it up).
+
+	Does nothing unless the buffer is [empty](#method.is_empty) and its capacity is larger than that.
+	*/
+	fn shrink(&mut self, cap: usize) -> Result<(), Self::Error>;
+	/**
+	Which kind of storage backs this buffer, e.g. for logging.
+
+	By default, this is [`BufferKind::Other`](enum.BufferKind.html#variant.Other).
+	*/
+	fn kind(&self) -> BufferKind {
+		BufferKind::Other
+	}
+	/**
+	Tell the OS how memory of the buffer is going to be accessed, see [`Advice`](enum.Advice.html).
+
+	This is only a hint, and buffers are free to ignore it, which is what they do by default.
+	[`MmapBuffer`](struct.MmapBuffer.html) passes it to `madvise()` (and ignores it on platforms that have no such thing),
+	and keeps applying it to the memory it reallocates.
+	*/
+	fn advise(&mut self, _advice: Advice) -> Result<(), Self::Error> {
+		Ok(())
+	}
+	/**
+	Make the buffer overwrite its memory with zeros before giving it back, both when it's dropped and when data is moved to another allocation,
+	so that data that was read (e.g. secrets) does not linger in freed memory.
+
+	Returns `true` if the buffer wipes its memory from now on (or always did),
+	and `false` if buffer cannot do that, which is the default; the buffer is left as it was in that case.
+	[`MlockBuffer`](struct.MlockBuffer.html) always does that, whether it's asked to or not.
+	*/
+	fn enable_zero_on_drop(&mut self) -> bool {
+		false
+	}
+	/**
+	Describe where data resides within the buffer, e.g. for reproducing issues with buffer management.
+
+	By default, this is derived from [`retained()`](#tymethod.retained) and [`len()`](#tymethod.len),
+	which is only accurate for buffers that keep their data in a single region of memory that never wraps around,
+	with all the free space at its end; buffers in this crate report their actual layout instead.
+
+	This is only available in tests, or with `debug-internals` feature enabled.
+	*/
+	#[cfg(any(test, feature = "debug-internals"))]
+	fn layout(&self) -> BufferLayout {
+		let end = self.retained().len();
+		BufferLayout {
+			capacity: self.capacity(),
+			start: end - self.len(),
+			len: self.len(),
+			appendable_len: self.capacity().saturating_sub(end),
+		}
+	}
+	/**
+	Assert internal consistency of the buffer (e.g. that data it holds is within bounds of allocated memory).
+
+	Implementations are expected to call this at the end of every method that modifies the buffer.
+	This is only available in debug builds, and does nothing by default.
+	*/
+	#[cfg(debug_assertions)]
+	fn check_invariants(&self) {}
+}
+
+/**
+Buffer that can be duplicated along with all the data it holds, see [`BufRefReader::try_clone()`](struct.BufRefReader.html#method.try_clone)
+
+This is not `Clone`, as duplicating the buffer involves allocation of the same kind that [`Buffer::new()`](trait.Buffer.html#tymethod.new) does,
+and it might fail just the same.
+*/
+pub trait CloneableBuffer: Buffer {
+	/**
+	Allocate new buffer of the same capacity, with the same data in it.
+
+	Consumed data that is still [retained](trait.Buffer.html#tymethod.retained) is retained by the copy as well.
+	*/
+	fn try_clone(&self) -> Result<Self, Self::Error>;
+}
+
+/// Storage that backs the buffer, as reported by [`Buffer::kind()`](trait.Buffer.html#method.kind)
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+#[non_exhaustive]
+pub enum BufferKind {
+	/// [`VecBuffer`](struct.VecBuffer.html)
+	Vec,
+	/// [`MmapBuffer`](struct.MmapBuffer.html)
+	MmapRing,
+	/// [`MlockBuffer`](struct.MlockBuffer.html)
+	#[cfg(feature = "mlock")]
+	Mlock,
+	/// Buffer implemented outside of this crate
+	Other,
+}
+
+/// Expected access pattern of the buffer memory, see [`Buffer::advise()`](trait.Buffer.html#method.advise)
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Advice {
+	/// No particular pattern, which is the default
+	Normal,
+	/// Memory is accessed sequentially, so it can be read ahead aggressively
+	Sequential,
+	/// Memory is accessed randomly, so reading ahead is pointless
+	Random,
+	/// Memory is going to be accessed soon
+	WillNeed,
+}
+
+/**
+How [`Buffer::enlarge()`](trait.Buffer.html#tymethod.enlarge) made room for new data
+
+Compaction only moves data that is already in the buffer, while reallocation also allocates new memory and copies data over,
+so lots of the latter suggest that initial capacity of the buffer is too small.
+*/
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum EnlargeResult {
+	/// `appendable()` part of the buffer had some room already
+	NoOp,
+	/// Data was moved within the buffer to make room at its end
+	Compacted,
+	/// Buffer was reallocated with larger capacity
+	Reallocated,
+}
+
+/**
+Internal layout of the buffer, as reported by [`Buffer::layout()`](trait.Buffer.html#method.layout)
+
+This is only available in tests, or with `debug-internals` feature enabled.
+*/
+#[cfg(any(test, feature = "debug-internals"))]
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct BufferLayout {
+	/// Same as [`Buffer::capacity()`](trait.Buffer.html#tymethod.capacity)
+	pub capacity: usize,
+	/// Where [`filled()`](trait.Buffer.html#tymethod.filled) part of the buffer begins within underlying memory
+	pub start: usize,
+	/// Same as [`Buffer::len()`](trait.Buffer.html#tymethod.len)
+	pub len: usize,
+	/// Size of [`appendable()`](trait.Buffer.html#tymethod.appendable) part of the buffer, without calling [`enlarge()`](trait.Buffer.html#tymethod.enlarge)
+	pub appendable_len: usize,
+}
+
+/// Error that [`VecBuffer`](struct.VecBuffer.html) emits when they fail to allocate memory
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum AllocError {
+	/// Requested capacity is larger than any allocation could ever be
+	CapacityOverflow,
+	/// Allocator failed to provide requested amount of memory
+	AllocFailed,
+}
+
+impl std::fmt::Display for AllocError {
+	fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+		match self {
+			AllocError::CapacityOverflow => write!(f, "buffer capacity overflow"),
+			AllocError::AllocFailed => write!(f, "failed to allocate buffer"),
+		}
+	}
+}
+
+impl std::error::Error for AllocError {}
+
+/*
+`vec![0; size]`, except that allocation failure is reported instead of aborting the process
+*/
+fn zeroed(size: usize) -> Result<Vec<u8>, AllocError> {
+	let mut buf = vec![];
+	resize(&mut buf, size)?;
+	Ok(buf)
+}
+
+/*
+`buf.resize(size, 0)`, except that allocation failure is reported instead of aborting the process
+*/
+fn resize(buf: &mut Vec<u8>, size: usize) -> Result<(), AllocError> {
+	if size > buf.len() {
+		buf.try_reserve_exact(size - buf.len()).map_err(|_| AllocError::AllocFailed)?;
+	}
+	buf.resize(size, 0);
+	Ok(())
+}
+
+/*
+overwrite memory with zeros before it is given back
+
+volatile writes keep compiler from optimizing away writes to memory that is never read again
+*/
+fn wipe(buf: &mut [u8]) {
+	for byte in buf.iter_mut() {
+		unsafe { std::ptr::write_volatile(byte, 0) };
+	}
+}
+
+/*
+`size * factor`, or None if it overflows usize,
+or if it's larger than any allocation could ever be (Vec panics past isize::MAX bytes)
+*/
+fn checked_size(size: usize, factor: usize) -> Option<usize> {
+	size.checked_mul(factor).filter(|&size| size <= isize::MAX as usize)
 }
 
 mod vec;
@@ -86,3 +407,194 @@ pub use vec::*;
 
 mod mmap;
 pub use mmap::*;
+
+
+#[cfg(feature = "mlock")]
+mod mlock;
+#[cfg(feature = "mlock")]
+pub use mlock::*;
+
+// buffer that only implements required methods, as it would be if implemented outside of this crate
+#[cfg(test)]
+pub(crate) struct PlainBuffer(pub(crate) VecBuffer);
+#[cfg(test)]
+impl Buffer for PlainBuffer {
+	type Error = AllocError;
+	fn new(cap: usize) -> Result<Self, AllocError> { VecBuffer::new(cap).map(PlainBuffer) }
+	fn appendable(&mut self) -> &mut [u8] { self.0.appendable() }
+	fn mark_appended(&mut self, amount: usize) { self.0.mark_appended(amount) }
+	fn scratch(&mut self) -> &mut [u8] { self.0.scratch() }
+	fn consume(&mut self, amount: usize) -> &[u8] { self.0.consume(amount) }
+	fn consume_and_peek(&mut self, amount: usize, peek: usize) -> &[u8] { self.0.consume_and_peek(amount, peek) }
+	fn consume_mut(&mut self, amount: usize) -> &mut [u8] { self.0.consume_mut(amount) }
+	fn unconsume(&mut self, amount: usize) { self.0.unconsume(amount) }
+	fn enlarge(&mut self) -> Result<EnlargeResult, AllocError> { self.0.enlarge() }
+	fn filled(&self) -> &[u8] { self.0.filled() }
+	fn retained(&self) -> &[u8] { self.0.retained() }
+	fn len(&self) -> usize { self.0.len() }
+	fn capacity(&self) -> usize { self.0.capacity() }
+	fn shrink(&mut self, cap: usize) -> Result<(), AllocError> { self.0.shrink(cap) }
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	#[test]
+	fn checked_size() {
+		assert_eq!(super::checked_size(0, 2), Some(0));
+		assert_eq!(super::checked_size(4096, 2), Some(8192));
+		assert_eq!(super::checked_size(usize::MAX/4, 2), Some(usize::MAX/4 * 2));
+		assert_eq!(super::checked_size(usize::MAX/4 + 1, 2), None);
+		assert_eq!(super::checked_size(usize::MAX/2 + 1, 2), None);
+		assert_eq!(super::checked_size(isize::MAX as usize, 1), Some(isize::MAX as usize));
+		assert_eq!(super::checked_size(isize::MAX as usize + 1, 1), None);
+	}
+
+	fn append_from<B: Buffer>(cap: usize)
+	where B::Error: std::fmt::Debug
+	{
+		let data: Vec<u8> = (0..cap*5/2).map(|i| (i % 251) as u8).collect();
+		let mut buf = B::new(cap).unwrap();
+		buf.append_from(&data[..cap/2]).unwrap();
+		assert_eq!(buf.capacity(), cap);
+		buf.consume(cap/4);
+		// does not fit, hence the buffer has to grow
+		buf.append_from(&data[cap/2..]).unwrap();
+		assert!(buf.capacity() >= data.len() - cap/4);
+		assert_eq!(buf.filled(), &data[cap/4..]);
+	}
+
+	#[test] fn append_from_vec()  { append_from::<VecBuffer>(16) }
+	#[test] fn append_from_mmap() { append_from::<MmapBuffer>(vmap::allocation_size()) }
+
+	fn appendable_uninit<B: Buffer>()
+	where B::Error: std::fmt::Debug
+	{
+		let mut buf = B::new(4096).unwrap();
+		buf.mark_appended(1000);
+		let len = buf.appendable().len();
+		let appendable = unsafe { buf.appendable_uninit() };
+		assert_eq!(appendable.len(), len);
+		for (i, c) in appendable[..5].iter_mut().zip(b"lorem") {
+			i.write(*c);
+		}
+		buf.consume(1000);
+		buf.mark_appended(5);
+		assert_eq!(buf.filled(), b"lorem");
+	}
+
+	#[test] fn appendable_uninit_vec()  { appendable_uninit::<VecBuffer>() }
+	#[test] fn appendable_uninit_mmap() { appendable_uninit::<MmapBuffer>() }
+
+	fn scratch<B: Buffer>()
+	where B::Error: std::fmt::Debug
+	{
+		let mut buf = B::new(4096).unwrap();
+		let cap = buf.capacity();
+		assert_eq!(buf.scratch().len(), cap);
+		// move data off the beginning of the buffer
+		buf.append_from(&[0; 1000]).unwrap();
+		buf.consume(1000);
+		let scratch = buf.scratch();
+		assert_eq!(scratch.len(), cap);
+		scratch[..5].copy_from_slice(b"lorem");
+		scratch[cap-5..].copy_from_slice(b"ipsum");
+		buf.mark_appended(cap);
+		assert_eq!(buf.len(), cap);
+		assert_eq!(&buf.filled()[..5], b"lorem");
+		assert_eq!(&buf.filled()[cap-5..], b"ipsum");
+		assert_eq!(buf.appendable().len(), 0);
+	}
+
+	#[test] fn scratch_vec()  { scratch::<VecBuffer>() }
+	#[test] fn scratch_mmap() { scratch::<MmapBuffer>() }
+
+	#[test]
+	#[should_panic(expected = "scratch")]
+	fn scratch_not_empty() {
+		let mut buf = VecBuffer::new(16).unwrap();
+		buf.mark_appended(1);
+		buf.scratch();
+	}
+
+	fn reserve<B: Buffer>(cap: usize)
+	where B::Error: std::fmt::Debug
+	{
+		let data: Vec<u8> = (0..cap).map(|i| (i % 251) as u8).collect();
+		let mut buf = B::new(cap).unwrap();
+		buf.append_from(&data).unwrap();
+		buf.consume(cap/4);
+		// moving data around is enough
+		buf.reserve(cap/4).unwrap();
+		assert_eq!(buf.capacity(), cap);
+		assert_eq!(buf.appendable().len(), cap/4);
+		// reallocation is not
+		buf.reserve(cap/2).unwrap();
+		assert_eq!(buf.capacity(), B::effective_capacity(cap/4*3 + cap/2));
+		assert_eq!(buf.filled(), &data[cap/4..]);
+	}
+
+	#[test] fn reserve_vec()  { reserve::<VecBuffer>(4096) }
+	#[test] fn reserve_mmap() { reserve::<MmapBuffer>(vmap::allocation_size() * 4) }
+
+	fn try_clone<B: CloneableBuffer>(cap: usize)
+	where B::Error: std::fmt::Debug
+	{
+		let data: Vec<u8> = (0..cap).map(|i| (i % 251) as u8).collect();
+		let mut buf = B::new(cap).unwrap();
+		buf.append_from(&data[..cap*3/4]).unwrap();
+		buf.consume(cap/2);
+		// wrap around the end of the ring, if there's one
+		buf.append_from(&data[cap*3/4..]).unwrap();
+		buf.append_from(&data[..cap/4]).unwrap();
+
+		let mut copy = buf.try_clone().unwrap();
+		assert_eq!(copy.capacity(), buf.capacity());
+		assert_eq!(copy.filled(), buf.filled());
+		assert_eq!(copy.retained(), buf.retained());
+		// the two are independent
+		copy.consume(1);
+		copy.consume_mut(1)[0] = 0xff;
+		assert_eq!(buf.filled()[1], data[cap/2 + 1]);
+		buf.consume(buf.len());
+		assert_eq!(copy.len(), cap*3/4 - 2);
+	}
+
+	#[test] fn try_clone_vec()  { try_clone::<VecBuffer>(16) }
+	#[test] fn try_clone_mmap() { try_clone::<MmapBuffer>(vmap::allocation_size()) }
+
+	#[test]
+	fn default_layout() {
+		let mut buf = PlainBuffer::new(16).unwrap();
+		assert_eq!(buf.layout(), buf.0.layout());
+		buf.mark_appended(10);
+		buf.consume(4);
+		assert_eq!(buf.layout(), buf.0.layout());
+		buf.mark_appended(6);
+		buf.enlarge().unwrap();
+		assert_eq!(buf.layout(), buf.0.layout());
+	}
+
+	#[test]
+	fn alloc_error() {
+		assert_eq!(VecBuffer::new(isize::MAX as usize + 1).unwrap_err(), AllocError::CapacityOverflow);
+		// fits into address space in theory, but not in practice
+		assert_eq!(VecBuffer::new(isize::MAX as usize).unwrap_err(), AllocError::AllocFailed);
+
+		let mut buf = VecBuffer::new(16).unwrap();
+		buf.mark_appended(16);
+		assert_eq!(buf.reserve(isize::MAX as usize).unwrap_err(), AllocError::CapacityOverflow);
+		assert_eq!(buf.reserve(isize::MAX as usize - 16).unwrap_err(), AllocError::AllocFailed);
+		// nothing is lost
+		assert_eq!(buf.len(), 16);
+	}
+
+	#[test]
+	fn new_overflow() {
+		// none of these should even try to allocate anything
+		assert!(VecBuffer::new(isize::MAX as usize + 1).is_err());
+		assert!(MmapBuffer::new(usize::MAX/2 + 1).is_err());
+		assert!(MmapBuffer::new(usize::MAX).is_err());
+	}
+}
@@ -4,24 +4,41 @@ pub struct VecBuffer {
 	// where actual data resides within the `buf`
 	start: usize,
 	end: usize,
+	// see Buffer::enable_zero_on_drop()
+	zero_on_drop: bool,
 }
+// only show the layout: buffer contents might be huge or sensitive
+impl std::fmt::Debug for VecBuffer {
+	fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+		f.debug_struct("VecBuffer")
+			.field("capacity", &self.buf.len())
+			.field("start", &self.start)
+			.field("len", &(self.end - self.start))
+			.finish()
+	}
+}
+
 impl super::Buffer for VecBuffer {
-	type Error = ();
-	fn new(size: usize) -> Result<Self, ()> {
-		let mut buf = Vec::with_capacity(size);
-		unsafe { buf.set_len(size); }
-		Ok(VecBuffer {
+	type Error = super::AllocError;
+	fn new(size: usize) -> Result<Self, super::AllocError> {
+		let size = super::checked_size(size, 1).ok_or(super::AllocError::CapacityOverflow)?;
+		let buf = super::zeroed(size)?;
+		let buf = VecBuffer {
 			buf,
 			start: 0, end: 0,
-		})
+			zero_on_drop: false,
+		};
+		#[cfg(debug_assertions)]
+		buf.check_invariants();
+		Ok(buf)
 	}
 	// make room for new data one way or the other
-	fn enlarge(&mut self) -> Result<(), ()> {
+	fn enlarge(&mut self) -> Result<super::EnlargeResult, super::AllocError> {
 		//if self.start == 0 && self.end == self.buf.len() {
-		if self.len() == self.buf.len() {
+		let result = if self.len() == self.buf.len() {
 			// this buffer is already full, double its size
-			self.buf.reserve(self.buf.len());
-			unsafe { self.buf.set_len(self.buf.len() * 2) };
+			self.grow()?;
+			super::EnlargeResult::Reallocated
 		} else if self.end == self.buf.len() {
 			// reallocate and fill existing buffer
 			if self.end - self.start != 0 {
@@ -29,22 +46,84 @@ impl super::Buffer for VecBuffer {
 			}
 			self.end -= self.start;
 			self.start = 0;
+			super::EnlargeResult::Compacted
 		} else {
 			// there's still some room in `appendable()`, nothing to do
+			super::EnlargeResult::NoOp
+		};
+		#[cfg(debug_assertions)]
+		self.check_invariants();
+		Ok(result)
+	}
+	fn grow(&mut self) -> Result<(), super::AllocError> {
+		let newsize = super::checked_size(self.buf.len(), 2).ok_or(super::AllocError::CapacityOverflow)?;
+		if self.start != 0 {
+			// move data to the beginning of the buffer so that all the free space ends up in `appendable()`
+			self.buf.copy_within(self.start..self.end, 0);
+			self.end -= self.start;
+			self.start = 0;
+		}
+		self.resize(newsize)?;
+		#[cfg(debug_assertions)]
+		self.check_invariants();
+		Ok(())
+	}
+	fn reserve(&mut self, additional: usize) -> Result<(), super::AllocError> {
+		if self.buf.len() - self.end < additional {
+			if self.start != 0 {
+				self.buf.copy_within(self.start..self.end, 0);
+				self.end -= self.start;
+				self.start = 0;
+			}
+			let newsize = self.end.checked_add(additional).ok_or(super::AllocError::CapacityOverflow)?;
+			if newsize > self.buf.len() {
+				let newsize = super::checked_size(newsize, 1).ok_or(super::AllocError::CapacityOverflow)?;
+				self.resize(newsize)?;
+			}
 		}
+		#[cfg(debug_assertions)]
+		self.check_invariants();
 		Ok(())
 	}
 	fn len(&self) -> usize {
 		self.end - self.start
 	}
+	fn capacity(&self) -> usize {
+		self.buf.len()
+	}
+	fn shrink(&mut self, cap: usize) -> Result<(), super::AllocError> {
+		if self.is_empty() && cap < self.buf.len() {
+			// there's no telling whether shrink_to_fit() moves the rest of it
+			self.wipe();
+			self.buf.truncate(cap);
+			self.buf.shrink_to_fit();
+			self.start = 0;
+			self.end = 0;
+		}
+		#[cfg(debug_assertions)]
+		self.check_invariants();
+		Ok(())
+	}
 	fn filled(&self) -> &[u8] {
 		&self.buf[ self.start .. self.end ]
 	}
+	// consumed data is only overwritten by compaction, which also resets `start`
+	fn retained(&self) -> &[u8] {
+		&self.buf[ .. self.end ]
+	}
 	fn appendable(&mut self) -> &mut [u8] {
 		&mut self.buf[ self.end .. ]
 	}
 	fn mark_appended(&mut self, amount: usize) {
 		self.end += amount;
+		#[cfg(debug_assertions)]
+		self.check_invariants();
+	}
+	fn scratch(&mut self) -> &mut [u8] {
+		assert!(self.is_empty(), "cannot use buffer as scratch space, it still holds {} bytes", self.len());
+		self.start = 0;
+		self.end = 0;
+		&mut self.buf[..]
 	}
 	/*
 	before:
@@ -62,10 +141,103 @@ impl super::Buffer for VecBuffer {
 		let amount = std::cmp::min(amount, self.len());
 		let start = self.start;
 		self.start += amount;
+		#[cfg(debug_assertions)]
+		self.check_invariants();
 		&self.buf[ start .. (start+amount) ]
 	}
+	fn consume_mut(&mut self, amount: usize) -> &mut [u8] {
+		let amount = std::cmp::min(amount, self.len());
+		let start = self.start;
+		self.start += amount;
+		#[cfg(debug_assertions)]
+		self.check_invariants();
+		&mut self.buf[ start .. (start+amount) ]
+	}
+	fn unconsume(&mut self, amount: usize) {
+		assert!(amount <= self.start, "cannot unconsume {} bytes, only {} are retained", amount, self.start);
+		self.start -= amount;
+		#[cfg(debug_assertions)]
+		self.check_invariants();
+	}
+	/*
+	after:
+	[  xxxxyyyy ]
+	   |  ||  |end
+	   |  ||start
+	   |-----|return value
+	*/
+	fn consume_and_peek(&mut self, amount: usize, peek: usize) -> &[u8] {
+		let amount = std::cmp::min(amount, self.len());
+		let peek = std::cmp::min(peek, self.len() - amount);
+		let start = self.start;
+		self.start += amount;
+		#[cfg(debug_assertions)]
+		self.check_invariants();
+		&self.buf[ start .. (start+amount+peek) ]
+	}
+	fn kind(&self) -> super::BufferKind {
+		super::BufferKind::Vec
+	}
+	fn enable_zero_on_drop(&mut self) -> bool {
+		self.zero_on_drop = true;
+		true
+	}
+	#[cfg(any(test, feature = "debug-internals"))]
+	fn layout(&self) -> super::BufferLayout {
+		super::BufferLayout {
+			capacity: self.buf.len(),
+			start: self.start,
+			len: self.end - self.start,
+			appendable_len: self.buf.len() - self.end,
+		}
+	}
+	#[cfg(debug_assertions)]
+	fn check_invariants(&self) {
+		assert!(self.start <= self.end, "start {} is past end {}", self.start, self.end);
+		assert!(self.end <= self.buf.len(), "end {} is past capacity {}", self.end, self.buf.len());
+	}
 }
 
+impl VecBuffer {
+	// same as super::resize(), except that old allocation is wiped before it is given back, if asked to
+	fn resize(&mut self, size: usize) -> Result<(), super::AllocError> {
+		if !self.zero_on_drop {
+			return super::resize(&mut self.buf, size);
+		}
+		let mut new = super::zeroed(size)?;
+		let len = std::cmp::min(size, self.buf.len());
+		new[..len].copy_from_slice(&self.buf[..len]);
+		self.wipe();
+		self.buf = new;
+		Ok(())
+	}
+	fn wipe(&mut self) {
+		if self.zero_on_drop {
+			super::wipe(&mut self.buf);
+		}
+	}
+}
+
+impl Drop for VecBuffer {
+	fn drop(&mut self) {
+		self.wipe();
+	}
+}
+
+impl super::CloneableBuffer for VecBuffer {
+	fn try_clone(&self) -> Result<Self, super::AllocError> {
+		let mut buf = super::zeroed(self.buf.len())?;
+		buf.copy_from_slice(&self.buf);
+		Ok(VecBuffer {
+			buf,
+			start: self.start,
+			end: self.end,
+			zero_on_drop: self.zero_on_drop,
+		})
+	}
+}
+
+#[cfg(test)]
 mod tests {
 	use super::*;
 	use crate::buffer::Buffer;
@@ -103,4 +275,109 @@ mod tests {
 		buf.enlarge().unwrap();
 		assert_eq!(buf.appendable().len(), 4096);
 	}
+
+	#[test]
+	fn enlarge_result() {
+		use crate::buffer::EnlargeResult;
+		let mut buf = VecBuffer::new(16).unwrap();
+		buf.mark_appended(8);
+		assert_eq!(buf.enlarge().unwrap(), EnlargeResult::NoOp);
+		buf.mark_appended(8);
+		buf.consume(4);
+		assert_eq!(buf.enlarge().unwrap(), EnlargeResult::Compacted);
+		assert_eq!(buf.capacity(), 16);
+		buf.mark_appended(4);
+		assert_eq!(buf.enlarge().unwrap(), EnlargeResult::Reallocated);
+		assert_eq!(buf.capacity(), 32);
+	}
+
+	#[test]
+	fn grow() {
+		let mut buf = VecBuffer::new(16).unwrap();
+		buf.mark_appended(10);
+		buf.consume(4);
+		buf.grow().unwrap();
+		// data is moved to the beginning, and everything else is appendable
+		assert_eq!(buf.capacity(), 32);
+		assert_eq!(buf.len(), 6);
+		assert_eq!(buf.appendable().len(), 26);
+	}
+
+	#[test]
+	fn invariants() {
+		// every step below is checked by check_invariants()
+		let mut buf = VecBuffer::new(16).unwrap();
+		for i in 0..1000 {
+			buf.enlarge().unwrap();
+			let n = std::cmp::min(i % 7, buf.appendable().len());
+			buf.mark_appended(n);
+			buf.consume(i % 5);
+			buf.consume_and_peek(i % 3, i % 2);
+		}
+		buf.consume(buf.len());
+		buf.shrink(16).unwrap();
+		assert_eq!(buf.capacity(), 16);
+	}
+
+	#[test]
+	#[cfg(debug_assertions)]
+	#[should_panic(expected = "is past capacity")]
+	fn invariants_violated() {
+		let mut buf = VecBuffer::new(16).unwrap();
+		// appending more than there's room for
+		buf.mark_appended(17);
+	}
+
+	#[test]
+	fn layout() {
+		let layout = |capacity, start, len, appendable_len| crate::buffer::BufferLayout { capacity, start, len, appendable_len };
+		let mut buf = VecBuffer::new(16).unwrap();
+		assert_eq!(buf.layout(), layout(16, 0, 0, 16));
+		buf.mark_appended(10);
+		buf.consume(4);
+		assert_eq!(buf.layout(), layout(16, 4, 6, 6));
+		buf.mark_appended(6);
+		buf.enlarge().unwrap();
+		// compacted
+		assert_eq!(buf.layout(), layout(16, 0, 12, 4));
+		buf.mark_appended(4);
+		buf.enlarge().unwrap();
+		assert_eq!(buf.layout(), layout(32, 0, 16, 16));
+	}
+
+	#[test]
+	fn shrink() {
+		let mut buf = VecBuffer::new(1024).unwrap();
+		buf.mark_appended(1024);
+		buf.enlarge().unwrap();
+		assert_eq!(buf.capacity(), 2048);
+
+		// buffer still holds data, should be noop
+		buf.shrink(1024).unwrap();
+		assert_eq!(buf.capacity(), 2048);
+
+		buf.consume(1024);
+		buf.shrink(1024).unwrap();
+		assert_eq!(buf.capacity(), 1024);
+		assert_eq!(buf.appendable().len(), 1024);
+	}
+
+	#[test]
+	fn zero_on_drop() {
+		let mut buf = VecBuffer::new(16).unwrap();
+		// not asked to
+		buf.appendable()[..3].copy_from_slice(b"abc");
+		buf.wipe();
+		assert_eq!(&buf.appendable()[..3], b"abc");
+
+		assert!(buf.enable_zero_on_drop());
+		buf.mark_appended(16);
+		// data survives reallocation
+		buf.grow().unwrap();
+		assert_eq!(&buf.filled()[..3], b"abc");
+		buf.appendable()[..3].copy_from_slice(b"def");
+		buf.mark_appended(3);
+		buf.wipe();
+		assert!(buf.buf.iter().all(|&c| c == 0));
+	}
 }
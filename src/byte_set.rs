@@ -0,0 +1,69 @@
+/**
+Set of bytes, e.g. delimiters for [`read_until_set()`](struct.BufRefReader.html#method.read_until_set)
+
+This is a 256-bit bitmap, so checking whether a byte is in the set is a single table lookup, whatever the size of the set.
+
+```
+use buf_ref_reader::*;
+
+# fn main() -> Result<(), Error> {
+let mut r = BufRefReader::<_, VecBuffer>::new(&b"lorem,ipsum;dolor"[..])?;
+let delims = ByteSet::from_bytes(b",;");
+assert_eq!(r.read_until_set(&delims)?, Some(&b"lorem,"[..]));
+assert_eq!(r.read_until_set(&delims)?, Some(&b"ipsum;"[..]));
+assert_eq!(r.read_until_set(&delims)?, Some(&b"dolor"[..]));
+# Ok(())
+# }
+```
+*/
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct ByteSet([u64; 4]);
+
+impl ByteSet {
+	/// Set that holds every one of `bytes`.
+	pub const fn from_bytes(bytes: &[u8]) -> Self {
+		let mut bits = [0; 4];
+		// no `for` loops in const fn
+		let mut i = 0;
+		while i < bytes.len() {
+			let c = bytes[i] as usize;
+			bits[c >> 6] |= 1 << (c & 63);
+			i += 1;
+		}
+		ByteSet(bits)
+	}
+
+	/// Set that holds every byte for which `f` returns `true`.
+	pub fn from_fn<F: FnMut(u8) -> bool>(mut f: F) -> Self {
+		let mut bits = [0; 4];
+		for c in 0..=255 {
+			if f(c) {
+				bits[(c >> 6) as usize] |= 1 << (c & 63);
+			}
+		}
+		ByteSet(bits)
+	}
+
+	/// Returns `true` if `c` is in the set.
+	#[inline]
+	pub fn contains(&self, c: u8) -> bool {
+		let c = c as usize;
+		(self.0[c >> 6] >> (c & 63)) & 1 != 0
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	#[test]
+	fn contains() {
+		let set = ByteSet::from_bytes(b"\x00?@\x7f\x80\xff");
+		for c in 0..=255 {
+			assert_eq!(set.contains(c), b"\x00?@\x7f\x80\xff".contains(&c), "{}", c);
+		}
+		assert_eq!(ByteSet::from_fn(|c| b"\x00?@\x7f\x80\xff".contains(&c)), set);
+		assert_eq!(ByteSet::from_bytes(b""), ByteSet::default());
+		assert!((0..=255).all(|c| ByteSet::from_fn(|_| true).contains(c)));
+	}
+}
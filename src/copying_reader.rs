@@ -0,0 +1,191 @@
+use std::io::{self, BufRead, Read};
+
+use crate::{BufRefReader, Buffer, Error};
+
+/**
+Reader that implements `std::io::BufRead` (and `Read`) on top of [`BufRefReader`](struct.BufRefReader.html)'s buffers.
+
+This is a drop-in replacement for `std::io::BufReader` that can use any of the [buffers](trait.Buffer.html) this crate provides.
+Unlike `BufRefReader` itself, it copies data into caller's buffers (e.g. with `BufRead::read_until()`),
+or allocates a new `String` for every line (with `BufRead::lines()`),
+so most of the performance benefits of this crate are lost.
+
+`BufRead::consume()` cannot fail, so if [`tee()`](struct.BufRefReader.html#method.tee) sink fails while data is consumed,
+the error is returned by the next call to `fill_buf()` or `read()` instead.
+
+```
+use buf_ref_reader::*;
+use std::io::BufRead;
+
+# fn main() -> std::io::Result<()> {
+let r = BufRefReader::<_, MmapBuffer>::new(&b"lorem\nipsum\ndolor"[..]).unwrap();
+let r = CopyingBufReader::new(r);
+for line in r.lines() {
+	let _line: String = line?;
+}
+# Ok(())
+# }
+```
+*/
+pub struct CopyingBufReader<R, B> {
+	reader: BufRefReader<R, B>,
+	// tee() sink error from consume(), reported by next fill_buf()
+	error: Option<io::Error>,
+}
+
+impl<R: Read, B: Buffer> CopyingBufReader<R, B>
+where Error: From<B::Error>
+{
+	/// Wraps given reader.
+	pub fn new(reader: BufRefReader<R, B>) -> Self {
+		CopyingBufReader {
+			reader,
+			error: None,
+		}
+	}
+
+	/// Unwraps underlying reader.
+	pub fn into_inner(self) -> BufRefReader<R, B> {
+		self.reader
+	}
+}
+
+impl<R: Read, B: Buffer> Read for CopyingBufReader<R, B>
+where Error: From<B::Error>
+{
+	fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+		let data = self.fill_buf()?;
+		let len = std::cmp::min(data.len(), buf.len());
+		buf[..len].copy_from_slice(&data[..len]);
+		self.consume(len);
+		Ok(len)
+	}
+}
+
+impl<R: Read, B: Buffer> BufRead for CopyingBufReader<R, B>
+where Error: From<B::Error>
+{
+	fn fill_buf(&mut self) -> io::Result<&[u8]> {
+		if let Some(err) = self.error.take() {
+			return Err(err);
+		}
+		if self.reader.buf.is_empty() {
+			if let Err(err) = self.reader.fill() {
+				return Err(into_io(err));
+			}
+		}
+		Ok(self.reader.buf.filled())
+	}
+
+	fn consume(&mut self, amount: usize) {
+		// data is consumed even if tee() sink fails, so the error is only postponed
+		if let Err(err) = self.reader.consume(amount) {
+			self.error = Some(into_io(err));
+		}
+	}
+}
+
+fn into_io(err: Error) -> io::Error {
+	match err {
+		Error::IO(err) => err,
+		err => io::Error::other(err),
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+	use crate::*;
+	use std::fmt::Debug;
+	use std::io::Write;
+
+	fn reader<B: Buffer>(data: &[u8]) -> CopyingBufReader<&[u8], B>
+	where
+		B::Error: Debug,
+		Error: From<B::Error>,
+	{
+		let r = BufRefReaderBuilder::new(data)
+			.capacity(16)
+			.build::<B>()
+			.unwrap();
+		CopyingBufReader::new(r)
+	}
+
+	fn lines<B: Buffer>()
+	where
+		B::Error: Debug,
+		Error: From<B::Error>,
+	{
+		let data = b"lorem\nipsum\r\n\ndolor";
+		let lines: Vec<String> = reader::<B>(data).lines()
+			.map(|line| line.unwrap())
+			.collect();
+		assert_eq!(lines, vec!["lorem", "ipsum", "", "dolor"]);
+	}
+
+	#[test] fn lines_vec()  { lines::<VecBuffer>() }
+	#[test] fn lines_mmap() { lines::<MmapBuffer>() }
+
+	fn read_until<B: Buffer>()
+	where
+		B::Error: Debug,
+		Error: From<B::Error>,
+	{
+		let mut r = reader::<B>(WORDS);
+		let mut words = WORDS.split(|&c| c == b'\n');
+		let mut line = vec![];
+		while r.read_until(b'\n', &mut line).unwrap() != 0 {
+			let mut word = words.next().unwrap().to_vec();
+			word.push(b'\n');
+			assert_eq!(line, word);
+			line.clear();
+		}
+		// this is .split(), hence empty string past last b'\n'
+		assert_eq!(words.next(), Some(&b""[..]));
+		assert_eq!(words.next(), None);
+	}
+
+	#[test] fn read_until_vec()  { read_until::<VecBuffer>() }
+	#[test] fn read_until_mmap() { read_until::<MmapBuffer>() }
+
+	#[test]
+	fn read() {
+		let mut r = reader::<VecBuffer>(WORDS);
+		let mut data = vec![];
+		r.read_to_end(&mut data).unwrap();
+		assert_eq!(&data[..], WORDS);
+	}
+
+	#[test]
+	fn tee_failed() {
+		struct FailingSink;
+		impl Write for FailingSink {
+			fn write(&mut self, _buf: &[u8]) -> io::Result<usize> {
+				Err(io::Error::other("sink is full"))
+			}
+			fn flush(&mut self) -> io::Result<()> {
+				Ok(())
+			}
+		}
+
+		let r = BufRefReaderBuilder::new(&b"lorem\nipsum\n"[..])
+			.build::<VecBuffer>()
+			.unwrap()
+			.tee(FailingSink);
+		let mut r = CopyingBufReader::new(r);
+		let mut line = vec![];
+		// BufRead::read_until() consumes the line before it returns, so the error is only seen afterwards
+		assert_eq!(r.read_until(b'\n', &mut line).unwrap(), 6);
+		assert_eq!(line, b"lorem\n");
+		let err = r.fill_buf().unwrap_err();
+		assert!(err.to_string().contains("sink is full"), "{}", err);
+		// error is only reported once, and data is consumed nevertheless
+		assert_eq!(r.fill_buf().unwrap(), b"ipsum\n");
+
+		let mut buf = [0; 3];
+		assert_eq!(r.read(&mut buf).unwrap(), 3);
+		assert!(r.read(&mut buf).is_err());
+		assert_eq!(r.read(&mut buf).unwrap(), 3);
+		assert_eq!(&buf, b"um\n");
+	}
+}
@@ -0,0 +1,143 @@
+use std::io::Read;
+use memchr::memchr;
+
+use crate::{BufRefReader, Buffer, Error};
+
+/**
+Reader that is limited to a single frame of the underlying [`BufRefReader`](struct.BufRefReader.html),
+as returned by [`read_frame_u32_le()`](struct.BufRefReader.html#method.read_frame_u32_le).
+
+Reads never go past the end of the frame, even if more data is already buffered:
+everything that follows the frame is left for the parent reader.
+Part of the frame that is not read through this reader is left for the parent reader as well.
+
+```
+use buf_ref_reader::*;
+
+# fn main() -> Result<(), Error> {
+let mut r = BufRefReader::<_, VecBuffer>::new(&b"\x08\x00\x00\x00foo,bar,baz"[..])?;
+if let Some(mut frame) = r.read_frame_u32_le()? {
+	while let Some(_field) = frame.read_until(b',')? {
+		// …
+	}
+}
+assert_eq!(r.read_until(b',')?, Some(&b"baz"[..]));
+# Ok(())
+# }
+```
+*/
+pub struct FrameReader<'a, R, B> {
+	reader: &'a mut BufRefReader<R, B>,
+	// how much of the frame is not read yet
+	remaining: usize,
+}
+
+impl<'a, R: Read, B: Buffer> FrameReader<'a, R, B>
+where Error: From<B::Error>
+{
+	pub(crate) fn new(reader: &'a mut BufRefReader<R, B>, len: usize) -> Self {
+		FrameReader {
+			reader,
+			remaining: len,
+		}
+	}
+
+	/// Amount of bytes of the frame that are not read yet.
+	pub fn remaining(&self) -> usize {
+		self.remaining
+	}
+
+	/**
+	Returns bytes up until and including `delim`, or until the end of the frame. If no content is available, returns `None`.
+
+	Returns:
+
+	- `Ok(Some(data))` with, well, data,
+	- `Ok(None)` if the end of the frame is reached (or EOF, if the frame is cut short by it),
+	- `Err(err)`: see `std::io::Read::read()`
+	*/
+	#[inline]
+	pub fn read_until(&mut self, delim: u8) -> Result<Option<&[u8]>, Error> {
+		// position within filled part of the buffer,
+		// from which to continue search for character
+		let mut pos = 0;
+		let len = loop {
+			let filled = self.reader.buf.filled();
+			let end = std::cmp::min(filled.len(), self.remaining);
+			if let Some(n) = memchr(delim, &filled[pos..end]) {
+				break pos+n+1;
+			}
+			if end == self.remaining {
+				// no need to buffer any more data than that
+				break end;
+			}
+			pos = end;
+			if self.reader.fill()?.is_none() {
+				// EOF within the frame
+				self.remaining = self.reader.buf.len();
+				break self.remaining;
+			}
+		};
+
+		if len == 0 {
+			Ok(None)
+		} else {
+			self.remaining -= len;
+			Ok(Some(self.reader.consume(len)?))
+		}
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use crate::*;
+	use std::fmt::Debug;
+
+	fn read_until<B: Buffer>()
+	where
+		B::Error: Debug,
+		Error: From<B::Error>,
+	{
+		let data = b"\x0e\x00\x00\x00lorem,ipsum,do\x05\x00\x00\x00sit,a\x04\x00\x00\x00met";
+		let mut r = BufRefReaderBuilder::new(&data[..])
+			.capacity(4)
+			.build::<B>()
+			.unwrap();
+
+		let mut frame = r.read_frame_u32_le().unwrap().unwrap();
+		assert_eq!(frame.remaining(), 14);
+		assert_eq!(frame.read_until(b',').unwrap(), Some(&b"lorem,"[..]));
+		assert_eq!(frame.read_until(b',').unwrap(), Some(&b"ipsum,"[..]));
+		// frame ends before the delimiter does
+		assert_eq!(frame.read_until(b',').unwrap(), Some(&b"do"[..]));
+		assert_eq!(frame.read_until(b',').unwrap(), None);
+		assert_eq!(frame.remaining(), 0);
+
+		let mut frame = r.read_frame_u32_le().unwrap().unwrap();
+		assert_eq!(frame.read_until(b',').unwrap(), Some(&b"sit,"[..]));
+		// next frame is already buffered, but is not returned
+		assert_eq!(frame.read_until(b',').unwrap(), Some(&b"a"[..]));
+		assert_eq!(frame.read_until(b',').unwrap(), None);
+
+		// frame is cut short by EOF
+		let mut frame = r.read_frame_u32_le().unwrap().unwrap();
+		assert_eq!(frame.read_until(b',').unwrap(), Some(&b"met"[..]));
+		assert_eq!(frame.read_until(b',').unwrap(), None);
+		assert!(r.read_frame_u32_le().unwrap().is_none());
+	}
+
+	#[test] fn read_until_vec()  { read_until::<VecBuffer>() }
+	#[test] fn read_until_mmap() { read_until::<MmapBuffer>() }
+
+	#[test]
+	fn leftovers() {
+		let mut r = BufRefReaderBuilder::new(&b"\x0b\x00\x00\x00lorem,ipsum\ndolor"[..])
+			.build::<VecBuffer>()
+			.unwrap();
+		let mut frame = r.read_frame_u32_le().unwrap().unwrap();
+		assert_eq!(frame.read_until(b',').unwrap(), Some(&b"lorem,"[..]));
+		// unread part of the frame as well as everything past it goes to the parent reader
+		assert_eq!(r.read_until(b'\n').unwrap(), Some(&b"ipsum\n"[..]));
+		assert_eq!(r.read_until(b'\n').unwrap(), Some(&b"dolor"[..]));
+	}
+}
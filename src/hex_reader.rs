@@ -0,0 +1,198 @@
+use std::io::{self, Read};
+
+/**
+Reader that decodes hex-encoded data from the underlying reader, e.g. to feed [`BufRefReader`](struct.BufRefReader.html) with decoded bytes.
+
+Both lowercase and uppercase digits are accepted, anything else (including whitespace) fails the read with `ErrorKind::InvalidData`,
+and so does an odd number of digits at EOF.
+Bytes that are decoded before an invalid digit are still returned first, and the error is reported by the following read.
+
+This is only available with `hex` feature enabled.
+
+```
+use buf_ref_reader::*;
+
+# fn main() -> Result<(), Error> {
+let src = HexDecodeReader::new(&b"6c6f72656d0a495053554d"[..]);
+let mut r = BufRefReader::<_, VecBuffer>::new(src)?;
+assert_eq!(r.read_until(b'\n')?, Some(&b"lorem\n"[..]));
+assert_eq!(r.read_until(b'\n')?, Some(&b"IPSUM"[..]));
+# Ok(())
+# }
+```
+*/
+pub struct HexDecodeReader<R> {
+	src: R,
+	// first digit of a pair that was split between reads
+	pending: Option<u8>,
+	// how many digits were read so far, for error messages
+	offset: u64,
+	// invalid digit that follows data that was already returned
+	error: Option<io::Error>,
+}
+
+impl<R: Read> HexDecodeReader<R> {
+	/// Wraps given reader.
+	pub fn new(src: R) -> Self {
+		HexDecodeReader {
+			src,
+			pending: None,
+			offset: 0,
+			error: None,
+		}
+	}
+
+	/// Unwraps underlying reader.
+	pub fn into_inner(self) -> R {
+		self.src
+	}
+}
+
+fn digit(c: u8) -> Option<u8> {
+	match c {
+		b'0'..=b'9' => Some(c - b'0'),
+		b'a'..=b'f' => Some(c - b'a' + 10),
+		b'A'..=b'F' => Some(c - b'A' + 10),
+		_ => None,
+	}
+}
+
+impl<R: Read> Read for HexDecodeReader<R> {
+	/*
+	digits are read right into `buf` and decoded in place:
+	n-th byte is only written after (2n)-th digit is decoded, so no digit is overwritten before it is used
+	*/
+	fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+		if buf.is_empty() {
+			return Ok(0);
+		}
+		if let Some(err) = self.error.take() {
+			return Err(err);
+		}
+		loop {
+			let read = self.src.read(buf)?;
+			if read == 0 {
+				return match self.pending {
+					None => Ok(0),
+					Some(_) => Err(io::Error::new(io::ErrorKind::InvalidData, "odd number of hex digits")),
+				};
+			}
+
+			let mut decoded = 0;
+			for i in 0..read {
+				let c = buf[i];
+				let d = match digit(c) {
+					Some(d) => d,
+					None => {
+						let err = io::Error::new(
+							io::ErrorKind::InvalidData,
+							format!("invalid hex digit {:?} at offset {}", c as char, self.offset + i as u64),
+						);
+						self.offset += i as u64;
+						self.pending = None;
+						if decoded == 0 {
+							return Err(err);
+						}
+						// digits that follow are lost, but those that precede are not
+						self.error = Some(err);
+						return Ok(decoded);
+					},
+				};
+				match self.pending.take() {
+					None => self.pending = Some(d),
+					Some(hi) => {
+						buf[decoded] = hi << 4 | d;
+						decoded += 1;
+					},
+				}
+			}
+			self.offset += read as u64;
+
+			if decoded != 0 {
+				return Ok(decoded);
+			}
+			// a single digit is not enough for a byte, and 0 would mean EOF
+		}
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+	use crate::*;
+	use std::fmt::Debug;
+	use std::fmt::Write;
+
+	struct OneByteReader<'a>(&'a [u8]);
+	impl Read for OneByteReader<'_> {
+		fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+			let len = std::cmp::min(1, buf.len());
+			self.0.read(&mut buf[..len])
+		}
+	}
+
+	fn words<B: Buffer>()
+	where
+		B::Error: Debug,
+		Error: From<B::Error>,
+	{
+		let mut hex = String::with_capacity(WORDS.len() * 2);
+		for c in WORDS {
+			write!(hex, "{:02x}", c).unwrap();
+		}
+
+		let mut r = BufRefReaderBuilder::new(HexDecodeReader::new(hex.as_bytes()))
+			.capacity(16)
+			.build::<B>()
+			.unwrap();
+		let mut words = WORDS.split(|&c| c == b'\n');
+		while let Some(line) = r.read_until(b'\n').unwrap() {
+			let mut word = words.next().unwrap().to_vec();
+			word.push(b'\n');
+			assert_eq!(line, &word[..]);
+		}
+		assert_eq!(words.next(), Some(&b""[..]));
+		assert_eq!(words.next(), None);
+	}
+
+	#[test] fn words_vec()  { words::<VecBuffer>() }
+	#[test] fn words_mmap() { words::<MmapBuffer>() }
+
+	#[test]
+	fn split_pairs() {
+		// every pair is split between reads
+		let mut data = vec![];
+		HexDecodeReader::new(OneByteReader(b"4C6f72454d")).read_to_end(&mut data).unwrap();
+		assert_eq!(&data[..], b"LorEM");
+	}
+
+	#[test]
+	fn invalid() {
+		let mut data = vec![];
+		let err = HexDecodeReader::new(&b"6c6f7"[..]).read_to_end(&mut data).unwrap_err();
+		assert_eq!(err.kind(), io::ErrorKind::InvalidData);
+		assert_eq!(err.to_string(), "odd number of hex digits");
+
+		let mut data = vec![];
+		let err = HexDecodeReader::new(&b"6c6f 72"[..]).read_to_end(&mut data).unwrap_err();
+		assert_eq!(err.kind(), io::ErrorKind::InvalidData);
+		assert_eq!(err.to_string(), "invalid hex digit ' ' at offset 4");
+	}
+
+	#[test]
+	fn invalid_after_valid() {
+		let mut r = HexDecodeReader::new(&b"4142zz"[..]);
+		let mut buf = [0; 16];
+		assert_eq!(r.read(&mut buf).unwrap(), 2);
+		assert_eq!(&buf[..2], b"AB");
+		let err = r.read(&mut buf).unwrap_err();
+		assert_eq!(err.kind(), io::ErrorKind::InvalidData);
+		assert_eq!(err.to_string(), "invalid hex digit 'z' at offset 4");
+
+		// same for the first digit of a pair
+		let mut data = vec![];
+		let err = HexDecodeReader::new(&b"41424z"[..]).read_to_end(&mut data).unwrap_err();
+		assert_eq!(err.to_string(), "invalid hex digit 'z' at offset 5");
+		assert_eq!(&data[..], b"AB");
+	}
+}
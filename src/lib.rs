@@ -13,9 +13,9 @@ Lack of extra allocations yields better read performance in situations where mos
 
 While being more performant, this approach also severely limits applicability of this reader:
 
-- it does not (and cannot) implement `BufRead` and cannot be used as a direct replacement for `BufReader`;
+- it does not (and cannot) implement `BufRead` and cannot be used as a direct replacement for `BufReader` (but see [`CopyingBufReader`](struct.CopyingBufReader.html));
 - returned values are only valid between calls to reading functions (i.e. they cannot outlive even a single loop cycle), and Rust's borrow checker will prevent you from using stale references;
-- consequently, `BufRefReader` cannot be turned into an `Iterator` (here's an easy way to think about it: what would `Iterator::collect()` return?);
+- consequently, `BufRefReader` cannot be turned into an `Iterator` (here's an easy way to think about it: what would `Iterator::collect()` return?), unless it copies every item (see [`lines_owned()`](struct.BufRefReader.html#method.lines_owned));
 - returned references are immutable;
 - obviously, there's also nothing that can return `String`s or `&str`s for you.
 
@@ -67,20 +67,58 @@ fn main() {
 */
 
 #![warn(missing_docs)]
+#![allow(clippy::tabs_in_doc_comments)]
 
 use quick_error::quick_error;
 
-use std::io::{self, Read};
-use memchr::memchr;
+use std::borrow::Cow;
+use std::fs::File;
+use std::io::{self, Read, Seek, Write};
+use std::path::Path;
+use std::ops::{Range, RangeInclusive};
+use std::time::{Duration, Instant};
+use memchr::{memchr, memchr_iter};
+use memchr::memmem::{self, Finder};
+use smallvec::SmallVec;
 
 mod buffer;
 pub use buffer::{
 	Buffer,
+	CloneableBuffer,
+	BufferKind,
+	EnlargeResult,
+	Advice,
+	AllocError,
 	VecBuffer,
 	MmapBuffer,
 };
+#[cfg(feature = "mlock")]
+pub use buffer::MlockBuffer;
+#[cfg(any(test, feature = "debug-internals"))]
+pub use buffer::BufferLayout;
+
+mod line_reader;
+pub use line_reader::LineReader;
+
+mod frame_reader;
+pub use frame_reader::FrameReader;
+
+mod copying_reader;
+pub use copying_reader::CopyingBufReader;
+
+mod merge_reader;
+pub use merge_reader::MergeReader;
+
+mod byte_set;
+pub use byte_set::ByteSet;
+
+#[cfg(feature = "hex")]
+mod hex_reader;
+#[cfg(feature = "hex")]
+pub use hex_reader::HexDecodeReader;
 
 use std::convert::From;
+use std::fmt;
 
 /**
 Buffering reader.
@@ -90,279 +128,5369 @@ See [module-level docs](index.html) for examples.
 pub struct BufRefReader<R, B> {
 	src: R,
 	buf: B,
+	config: Config,
+	// amount of bytes consumed so far
+	position: u64,
+	// whether the last read from `src` hit EOF
+	eof: bool,
+	// whether `src` failed with poison_on_error set
+	poisoned: bool,
+	// prebuilt searcher for `config.record_delimiter`
+	// (boxed, as it is moved out of the reader and back for every search)
+	finder: Option<Box<Finder<'static>>>,
+	// time spent in `src.read()`, if `config.time_reads` is set
+	read_time: Duration,
+	// where read_until_budgeted() gave up: delimiter, `position` at the time, and offset within `filled()` to resume from
+	scan_resume: Option<(u8, u64, usize)>,
+	// what fill() had to do the last time the buffer was full
+	last_enlarge: Option<EnlargeResult>,
+	// where consumed data is copied to, see tee()
+	tee: Option<Box<dyn Write + Send>>,
+	// `position` up to which data was already copied to `tee`, so that unread data is not copied twice
+	teed: u64,
+	// see stats()
+	tokens: u64,
+	min_token_len: usize,
+	max_token_len: usize,
+}
+
+// neither reader nor buffer contents are shown
+impl<R, B: Buffer> fmt::Debug for BufRefReader<R, B> {
+	fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+		f.debug_struct("BufRefReader")
+			.field("capacity", &self.buf.capacity())
+			.field("buffered", &self.buf.len())
+			.field("position", &self.position)
+			.field("eof", &self.eof)
+			.field("poisoned", &self.poisoned)
+			.finish()
+	}
+}
+
+/**
+Reader options that can be shared between multiple builders.
+
+Unlike [`BufRefReaderBuilder`](struct.BufRefReaderBuilder.html), this does not hold the reader itself,
+hence it can be cloned and reused for any number of sources:
+
+```
+use buf_ref_reader::*;
+
+# fn main() -> Result<(), Error> {
+let config = Config {
+	capacity: 4096,
+	..Config::default()
+};
+
+for src in &[&b"foo\nbar"[..], &b"baz\n"[..]] {
+	let mut r = BufRefReaderBuilder::from_config(*src, config.clone())
+		.build::<VecBuffer>()?;
+	while let Some(_line) = r.read_until(b'\n')? {
+		// …
+	}
+}
+# Ok(())
+# }
+```
+*/
+#[derive(Clone, Debug)]
+pub struct Config {
+	/// Initial buffer capacity.
+	pub capacity: usize,
+	/// Whether to give memory back after buffer had to grow, see [`AutoShrink`](enum.AutoShrink.html).
+	pub auto_shrink: AutoShrink,
+	/// Never grow the buffer past its initial capacity, see [`BufRefReaderBuilder::fixed()`](struct.BufRefReaderBuilder.html#method.fixed).
+	pub fixed: bool,
+	/// Transparently retry reads that fail with `ErrorKind::Interrupted`, see [`BufRefReaderBuilder::retry_interrupted()`](struct.BufRefReaderBuilder.html#method.retry_interrupted).
+	pub retry_interrupted: bool,
+	/// Allocate buffer of exactly the initial capacity, see [`BufRefReaderBuilder::exact_capacity()`](struct.BufRefReaderBuilder.html#method.exact_capacity).
+	pub exact_capacity: bool,
+	/// Refuse to read from the underlying reader after it failed once, see [`BufRefReaderBuilder::poison_on_error()`](struct.BufRefReaderBuilder.html#method.poison_on_error).
+	pub poison_on_error: bool,
+	/// Delimiter for [`BufRefReader::read_record()`](struct.BufRefReader.html#method.read_record), see [`BufRefReaderBuilder::record_delimiter()`](struct.BufRefReaderBuilder.html#method.record_delimiter).
+	pub record_delimiter: Option<Vec<u8>>,
+	/// How fast the buffer grows while looking for a delimiter, see [`BufRefReaderBuilder::readahead()`](struct.BufRefReaderBuilder.html#method.readahead).
+	pub readahead: usize,
+	/// Measure time spent reading from the underlying reader, see [`BufRefReaderBuilder::time_reads()`](struct.BufRefReaderBuilder.html#method.time_reads).
+	pub time_reads: bool,
+	/// Grow the buffer by this many bytes instead of doubling its capacity, see [`BufRefReaderBuilder::increment()`](struct.BufRefReaderBuilder.html#method.increment).
+	pub increment: Option<usize>,
+	/// Treat data that is not followed by a delimiter at EOF as an error, see [`BufRefReaderBuilder::require_final_delim()`](struct.BufRefReaderBuilder.html#method.require_final_delim).
+	pub require_final_delim: bool,
+	/// Never read into less free space than this, see [`BufRefReaderBuilder::min_read()`](struct.BufRefReaderBuilder.html#method.min_read).
+	pub min_read: usize,
+	/// Access pattern hint for the buffer, see [`BufRefReaderBuilder::advise()`](struct.BufRefReaderBuilder.html#method.advise).
+	pub advice: Advice,
+	/// Check that the underlying reader still has data where it used to after seeking, see [`BufRefReaderBuilder::validate_after_seek()`](struct.BufRefReaderBuilder.html#method.validate_after_seek).
+	pub validate_after_seek: bool,
+	/// Overwrite buffer memory with zeros before it is given back, see [`BufRefReaderBuilder::zero_on_drop()`](struct.BufRefReaderBuilder.html#method.zero_on_drop).
+	pub zero_on_drop: bool,
+}
+impl Default for Config {
+	fn default() -> Self {
+		Config {
+			capacity: 8192,
+			auto_shrink: AutoShrink::Never,
+			fixed: false,
+			retry_interrupted: true,
+			exact_capacity: false,
+			poison_on_error: false,
+			record_delimiter: None,
+			readahead: 1,
+			time_reads: false,
+			increment: None,
+			require_final_delim: false,
+			min_read: 1,
+			advice: Advice::Normal,
+			validate_after_seek: false,
+			zero_on_drop: false,
+		}
+	}
+}
+
+impl Config {
+	// setters do not check anything, so that options are only validated here, whether they come from a builder or from a Config
+	fn check(&self) -> Result<(), &'static str> {
+		if self.capacity == 0 {
+			return Err("capacity must be larger than 0");
+		}
+		if self.increment == Some(0) {
+			return Err("increment must be larger than 0");
+		}
+		if self.min_read == 0 {
+			return Err("min_read must be larger than 0");
+		}
+		if self.record_delimiter.as_ref().is_some_and(|delim| delim.is_empty()) {
+			return Err("record delimiter must not be empty");
+		}
+		if self.fixed && self.increment.is_some() {
+			return Err("increment is set for a fixed buffer, which never grows");
+		}
+		Ok(())
+	}
+}
+
+/**
+Policy for shrinking the buffer after it had to grow to fit a large chunk of data.
+
+Buffer is only ever shrunk when it's empty (i.e. all the data it holds has been consumed),
+and only back to the initial capacity, so no live data is ever copied around.
+*/
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum AutoShrink {
+	/// Keep the buffer as large as it got (default).
+	Never,
+	/// Shrink the buffer back to its initial capacity whenever it gets empty while being larger than given size.
+	Below(usize),
+}
+
+/**
+Builder for [`BufRefReader`](struct.BufRefReader.html).
+
+See [module-level docs](index.html) for examples.
+*/
+pub struct BufRefReaderBuilder<R> {
+	src: R,
+	config: Config,
+}
+impl<R: Read> BufRefReaderBuilder<R> {
+	/// Creates new builder with given reader and default options.
+	pub fn new(src: R) -> Self {
+		Self::from_config(src, Config::default())
+	}
+
+	/**
+	Creates new builder with given reader and 4 KiB of initial capacity.
+
+	This is a single memory page on most platforms, i.e. the smallest [`MmapBuffer`](struct.MmapBuffer.html) there is,
+	and is suitable for short tokens (e.g. words or short lines),
+	or for when there are lots of readers that are alive at the same time.
+	*/
+	pub fn small(src: R) -> Self {
+		Self::new(src)
+			.capacity(4*1024)
+	}
+
+	/**
+	Creates new builder with given reader and 256 KiB of initial capacity.
+
+	This suits bulk processing of large inputs, as every read from the underlying reader fetches more data at once,
+	and it leaves enough room for most tokens to never require buffer to grow.
+	*/
+	pub fn large(src: R) -> Self {
+		Self::new(src)
+			.capacity(256*1024)
+	}
+
+	/// Creates new builder with given reader and options from previously obtained [`Config`](struct.Config.html).
+	pub fn from_config(src: R, config: Config) -> Self {
+		BufRefReaderBuilder {
+			src,
+			config,
+		}
+	}
+
+	/// Returns options set so far, e.g. to use them with another reader later on.
+	pub fn config(&self) -> Config {
+		self.config.clone()
+	}
+
+	/**
+	Set initial buffer capacity.
+
+	Capacity must be larger than 0, otherwise [`build()`](#method.build) fails with [`Error::InvalidConfig`](enum.Error.html#variant.InvalidConfig).
+	*/
+	pub fn capacity(mut self, bufsize: usize) -> Self {
+		self.config.capacity = bufsize;
+		self
+	}
+
+	/// Set policy for shrinking the buffer back after it grows (see [`AutoShrink`](enum.AutoShrink.html)).
+	pub fn auto_shrink(mut self, policy: AutoShrink) -> Self {
+		self.config.auto_shrink = policy;
+		self
+	}
+
+	/**
+	Forbid buffer from growing past its initial capacity.
+
+	Buffer is still allowed to move its data around to make room for new data,
+	but requests that cannot be fulfilled without reallocation (e.g. tokens that are longer than the buffer)
+	fail with [`Error::BufferFull`](enum.Error.html#variant.BufferFull).
+	*/
+	pub fn fixed(mut self, fixed: bool) -> Self {
+		self.config.fixed = fixed;
+		self
+	}
+
+	/**
+	Whether to retry reads that fail with `ErrorKind::Interrupted` (default), just like `std::io` does,
+	or to return them as [`Error::IO`](enum.Error.html#variant.IO) for the caller to observe.
+
+	Interrupted read is safe to call again: no data is lost either way.
+	*/
+	pub fn retry_interrupted(mut self, retry: bool) -> Self {
+		self.config.retry_interrupted = retry;
+		self
+	}
+
+	/**
+	Ask buffer to allocate exactly [`capacity`](#method.capacity) bytes (see [`Buffer::new_exact()`](trait.Buffer.html#method.new_exact)),
+	instead of at least that many.
+	*/
+	pub fn exact_capacity(mut self, exact: bool) -> Self {
+		self.config.exact_capacity = exact;
+		self
+	}
+
+	/**
+	After the underlying reader fails with [`Error::IO`](enum.Error.html#variant.IO),
+	fail every subsequent attempt to read from it with [`Error::Poisoned`](enum.Error.html#variant.Poisoned),
+	until the reader is [`reset()`](struct.BufRefReader.html#method.reset).
+
+	This is for parsers that cannot tell what state the underlying reader is left in after an error,
+	and would rather stop than risk reading data that is out of frame.
+	Data that was buffered before the error is still returned as usual.
+	Reads that are [interrupted](#method.retry_interrupted) and retried do not poison the reader.
+	*/
+	pub fn poison_on_error(mut self, poison: bool) -> Self {
+		self.config.poison_on_error = poison;
+		self
+	}
+
+	/**
+	Once the buffer is full, grow it by `incr` bytes (or more, if buffer needs to round it up, see [`Buffer::reserve()`](trait.Buffer.html#method.reserve)),
+	instead of doubling its capacity (default).
+
+	This keeps memory usage closer to the size of the largest token, at the expense of more reallocations (and copying) for tokens that are much larger than `incr`.
+	[Read-ahead](#method.readahead) is not affected by this.
+
+	`incr` must be larger than 0, otherwise [`build()`](#method.build) fails with [`Error::InvalidConfig`](enum.Error.html#variant.InvalidConfig).
+	*/
+	pub fn increment(mut self, incr: usize) -> Self {
+		self.config.increment = Some(incr);
+		self
+	}
+
+	/**
+	Grow the buffer `n` times per read while looking for a delimiter (e.g. with [`read_until()`](struct.BufRefReader.html#method.read_until))
+	that is not found in the data read so far, instead of only growing it once it is full.
+
+	Reads that are issued during the search are thus getting larger and larger,
+	so it takes fewer of them (and fewer syscalls) to buffer a long token.
+	This only kicks in after the first read of the search did not yield the delimiter,
+	so short tokens are still read with buffer of initial capacity.
+
+	Default is 1, which disables read-ahead, and so does 0. Buffers of [fixed](#method.fixed) size are never grown, whatever `n` is,
+	and buffers that do not implement [`Buffer::grow()`](trait.Buffer.html#method.grow) only grow once they are full, as usual.
+	*/
+	pub fn readahead(mut self, n: usize) -> Self {
+		self.config.readahead = n;
+		self
+	}
+
+	/**
+	Measure wall-clock time spent in `read()` of the underlying reader, see [`BufRefReader::read_time()`](struct.BufRefReader.html#method.read_time).
+
+	This tells whether parsing is bound by IO or by CPU. It is disabled by default, so that clock is not queried twice for every read.
+	*/
+	pub fn time_reads(mut self, time: bool) -> Self {
+		self.config.time_reads = time;
+		self
+	}
+
+	/**
+	Make [`read_until()`](struct.BufRefReader.html#method.read_until) fail with [`Error::MissingFinalDelimiter`](enum.Error.html#variant.MissingFinalDelimiter)
+	if the stream does not end with a delimiter, instead of returning whatever follows the last delimiter as the last token (default).
+
+	This is for strict protocols that require every record to be terminated, where unterminated record is a sign of truncated stream.
+	*/
+	pub fn require_final_delim(mut self, require: bool) -> Self {
+		self.config.require_final_delim = require;
+		self
+	}
+
+	/**
+	Make room for at least `n` bytes before every read from the underlying reader,
+	instead of reading into whatever free space is left at the end of the buffer (default).
+
+	This is for readers that are slow with small reads, e.g. block devices that have to read a whole block anyway.
+	Buffer is compacted or, if that's not enough, grown to make room;
+	buffers of [fixed](#method.fixed) size are never grown for that, so reads might still be smaller than `n` if the buffer is mostly full.
+
+	`n` must be larger than 0, otherwise [`build()`](#method.build) fails with [`Error::InvalidConfig`](enum.Error.html#variant.InvalidConfig). Default is 1.
+	*/
+	pub fn min_read(mut self, n: usize) -> Self {
+		self.config.min_read = n;
+		self
+	}
+
+	/**
+	Tell the OS how buffer memory is going to be accessed, see [`Buffer::advise()`](trait.Buffer.html#method.advise).
+
+	This is applied to the buffer as soon as [`build()`](#method.build) allocates it,
+	and is only honored by buffers that are backed by memory maps, i.e. [`MmapBuffer`](struct.MmapBuffer.html).
+	Buffers passed to [`build_with()`](#method.build_with) are left as they are.
+
+	Note that buffer memory is not backed by a file, so there's nothing for the OS to read ahead:
+	reading a file line by line with a 64K buffer shows no measurable difference with any of the hints (see `bufref_advise_*` benchmarks).
+	*/
+	pub fn advise(mut self, advice: Advice) -> Self {
+		self.config.advice = advice;
+		self
+	}
+
+	/**
+	Make [`BufRefReader::seek()`](struct.BufRefReader.html#method.seek) read from the new position right away if the underlying reader has already been past it,
+	and fail with [`Error::SourceChanged`](enum.Error.html#variant.SourceChanged) if there's no data there anymore.
+
+	This catches files that are truncated (e.g. logs that are rotated) while being read,
+	although only at the time of the seek: there's no telling what happens to the file afterwards.
+	*/
+	pub fn validate_after_seek(mut self, validate: bool) -> Self {
+		self.config.validate_after_seek = validate;
+		self
+	}
+
+	/**
+	Overwrite buffer memory with zeros before it is freed, both when the reader is dropped and when the buffer is moved to a larger allocation,
+	see [`Buffer::enable_zero_on_drop()`](trait.Buffer.html#method.enable_zero_on_drop).
+
+	This is a best-effort measure for readers of sensitive data (e.g. keys or passwords):
+	writes are volatile so that compiler does not optimize them away,
+	but copies made elsewhere (e.g. tokens returned by [`read_until_joined()`](struct.BufRefReader.html#method.read_until_joined),
+	or data that is still held by the underlying reader) are not covered,
+	and neither is memory that the OS swaps out, see [`MlockBuffer`](struct.MlockBuffer.html) for that.
+
+	This is applied to the buffer as soon as [`build()`](#method.build) allocates it,
+	which fails with [`Error::InvalidConfig`](enum.Error.html#variant.InvalidConfig) if buffer does not support it.
+	Buffers passed to [`build_with()`](#method.build_with) are left as they are.
+	*/
+	pub fn zero_on_drop(mut self, zero: bool) -> Self {
+		self.config.zero_on_drop = zero;
+		self
+	}
+
+	/**
+	Capacity that the buffer is going to have once the reader is [built](#method.build) with buffer of type `B`.
+
+	This might be larger than requested [`capacity`](#method.capacity) (see [`Buffer::effective_capacity()`](trait.Buffer.html#method.effective_capacity)),
+	e.g. [`MmapBuffer`](struct.MmapBuffer.html) rounds it up to the page size (which is not necessarily 4 KiB).
+	*/
+	pub fn effective_capacity<B: Buffer>(&self) -> usize {
+		B::effective_capacity(self.config.capacity)
+	}
+
+	/**
+	Set multi-byte delimiter for [`read_record()`](struct.BufRefReader.html#method.read_record).
+
+	Searcher for this delimiter is built once, along with the reader,
+	and is then reused for every record, as well as by [`read_until_bytes()`](struct.BufRefReader.html#method.read_until_bytes) if it is given the same delimiter.
+	*/
+	pub fn record_delimiter(mut self, delim: &[u8]) -> Self {
+		self.config.record_delimiter = Some(delim.to_vec());
+		self
+	}
+
+	/**
+	Create actual reader.
+
+	Fails with [`Error::InvalidConfig`](enum.Error.html#variant.InvalidConfig) if options make no sense together
+	(e.g. [`increment()`](#method.increment) of a [fixed](#method.fixed) buffer, which never grows),
+	or with whatever error buffer emits if it fails to allocate.
+	*/
+	pub fn build<B: Buffer>(self) -> Result<BufRefReader<R, B>, Error>
+	where Error: From<B::Error>
+	{
+		self.config.check().map_err(Error::InvalidConfig)?;
+		let mut buf = if self.config.exact_capacity {
+			B::new_exact(self.config.capacity)?
+		} else {
+			B::new(self.config.capacity)?
+		};
+		if self.config.advice != Advice::Normal {
+			buf.advise(self.config.advice)?;
+		}
+		if self.config.zero_on_drop && !buf.enable_zero_on_drop() {
+			return Err(Error::InvalidConfig("zero_on_drop is not supported by the buffer"));
+		}
+		Ok(self.build_with(buf))
+	}
+
+	/**
+	Create actual reader that uses already allocated buffer, e.g. one that is [taken](struct.BufRefReader.html#method.take_buffer) from another reader.
+
+	Buffer keeps its capacity, so [`capacity`](#method.capacity) is only used as the size to [shrink](enum.AutoShrink.html) the buffer back to.
+	Data that is buffered but not consumed yet is returned by the reader first, as if it was read from `src`.
+	*/
+	pub fn build_with<B: Buffer>(self, buf: B) -> BufRefReader<R, B> {
+		let finder = self.config.record_delimiter.as_ref()
+			.map(|delim| Box::new(Finder::new(delim).into_owned()));
+		BufRefReader {
+			src: self.src,
+			buf,
+			config: self.config,
+			position: 0,
+			eof: false,
+			poisoned: false,
+			finder,
+			read_time: Duration::ZERO,
+			scan_resume: None,
+			last_enlarge: None,
+			tee: None,
+			teed: 0,
+			tokens: 0,
+			min_token_len: usize::MAX,
+			max_token_len: 0,
+		}
+	}
+}
+
+impl<T: AsRef<[u8]>> BufRefReaderBuilder<io::Cursor<T>> {
+	/**
+	Creates new builder with default options that reads from in-memory data, e.g. `Vec<u8>`, `String`, or `&str`.
+
+	Resulting reader owns the data, hence there's no need to keep it alive separately, or to turn it into `&[u8]` first:
+
+	```
+	use buf_ref_reader::*;
+
+	# fn main() -> Result<(), Error> {
+	let mut r = BufRefReaderBuilder::from_bytes("lorem ipsum")
+		.build::<VecBuffer>()?;
+	assert_eq!(r.read_until(b' ')?, Some(&b"lorem "[..]));
+	# Ok(())
+	# }
+	```
+	*/
+	pub fn from_bytes(data: T) -> Self {
+		Self::new(io::Cursor::new(data))
+	}
 }
 
-/**
-Builder for [`BufRefReader`](struct.BufRefReader.html).
+/// Offsets of field delimiters within a record, see [`BufRefReader::read_record_offsets()`](struct.BufRefReader.html#method.read_record_offsets).
+pub type FieldOffsets = SmallVec<[usize; 16]>;
+
+/// Token, and index of the needle that terminates it, see [`BufRefReader::read_until_any_seq()`](struct.BufRefReader.html#method.read_until_any_seq).
+pub type SeqToken<'a> = (&'a [u8], Option<usize>);
+
+/// Token, and data that is buffered past it, see [`BufRefReader::read_until_and_rest()`](struct.BufRefReader.html#method.read_until_and_rest).
+pub type TokenAndRest<'a> = (&'a [u8], &'a [u8]);
+
+/**
+Flattened result of reading functions like [`BufRefReader::read_until()`](struct.BufRefReader.html#method.read_until),
+that can be matched without nesting `Option` in `Result`:
+
+```
+use buf_ref_reader::*;
+
+# fn main() -> Result<(), Error> {
+let mut r = BufRefReader::<_, VecBuffer>::new(&b"lorem\nipsum"[..])?;
+loop {
+	match r.read_until_flat(b'\n') {
+		ReadResult::Data(_line) => continue,
+		ReadResult::Eof => break,
+		ReadResult::Err(err) => return Err(err),
+	}
+}
+// or, with any other reading function:
+match r.read(4).into() {
+	ReadResult::Eof => (),
+	_ => unreachable!(),
+}
+# Ok(())
+# }
+```
+*/
+#[derive(Debug)]
+pub enum ReadResult<'a> {
+	/// Same as `Ok(Some(data))`
+	Data(&'a [u8]),
+	/// Same as `Ok(None)`, i.e. no more data is available
+	Eof,
+	/// Same as `Err(err)`
+	Err(Error),
+}
+
+impl<'a> From<Result<Option<&'a [u8]>, Error>> for ReadResult<'a> {
+	fn from(result: Result<Option<&'a [u8]>, Error>) -> Self {
+		match result {
+			Ok(Some(data)) => ReadResult::Data(data),
+			Ok(None) => ReadResult::Eof,
+			Err(err) => ReadResult::Err(err),
+		}
+	}
+}
+
+/// Outcome of [`BufRefReader::read_until_budgeted()`](struct.BufRefReader.html#method.read_until_budgeted).
+#[derive(Debug, PartialEq, Eq)]
+pub enum ScanState<'a> {
+	/// Bytes up until and including the delimiter, or until EOF mark
+	Token(&'a [u8]),
+	/// Budget is exhausted before the delimiter is found; call again to carry on
+	Pending,
+	/// No more data is available
+	Eof,
+}
+
+/**
+Statistics of data returned by the reader, as reported by [`BufRefReader::stats()`](struct.BufRefReader.html#method.stats)
+
+Every chunk of data that is consumed by reading functions (e.g. a line returned by [`read_until()`](struct.BufRefReader.html#method.read_until),
+or a chunk returned by [`read()`](struct.BufRefReader.html#method.read)) counts as a token, along with its delimiter, if any.
+Data that is skipped (e.g. by [`skip_bom()`](struct.BufRefReader.html#method.skip_bom)) only counts towards `bytes`.
+*/
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct Stats {
+	/// Amount of bytes consumed so far, same as [`BufRefReader::position()`](struct.BufRefReader.html#method.position)
+	pub bytes: u64,
+	/// Number of tokens returned so far
+	pub tokens: u64,
+	/// Length of the shortest token, or `None` if there were no tokens yet
+	pub min_token_len: Option<usize>,
+	/// Length of the longest token, or `None` if there were no tokens yet
+	pub max_token_len: Option<usize>,
+}
+
+/// Line terminator, as reported by [`BufRefReader::read_line_with_ending()`](struct.BufRefReader.html#method.read_line_with_ending).
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum LineEnding {
+	/// `\n`
+	Lf,
+	/// `\r\n`
+	CrLf,
+	/// Line is not terminated, as it is the last one in the stream
+	None,
+}
+
+/**
+Token returned by [`BufRefReader::next_token()`](struct.BufRefReader.html#method.next_token).
+
+Token is already consumed by the time it's returned, and guard only holds the borrow of the reader,
+so that it's obvious where the token has to be released for the next one to be fetched:
+either drop the guard, or let it go out of scope.
+Whatever has to outlive the guard must be copied or parsed out of it first.
+*/
+#[derive(Debug, PartialEq, Eq)]
+pub struct TokenGuard<'a> {
+	data: &'a [u8],
+}
+
+impl<'a> TokenGuard<'a> {
+	/// Unwraps the token, keeping the borrow of the reader.
+	pub fn into_inner(self) -> &'a [u8] {
+		self.data
+	}
+}
+
+impl std::ops::Deref for TokenGuard<'_> {
+	type Target = [u8];
+	fn deref(&self) -> &[u8] {
+		self.data
+	}
+}
+
+impl AsRef<[u8]> for TokenGuard<'_> {
+	fn as_ref(&self) -> &[u8] {
+		self.data
+	}
+}
+
+/// Byte order mark, as detected by [`BufRefReader::skip_bom()`](struct.BufRefReader.html#method.skip_bom).
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Bom {
+	/// `EF BB BF`
+	Utf8,
+	/// `FF FE`
+	Utf16Le,
+	/// `FE FF`
+	Utf16Be,
+}
+
+quick_error! {
+	/// Error type that reading functions might emit
+	#[derive(Debug)]
+	pub enum Error {
+		/// Error reading from actual reader
+		IO(err: io::Error) { from() }
+		/// Indicates failure to create/grow buffer
+		Buf(err: vmap::Error) { from() }
+		/// Requested data is not buffered yet, and getting it requires reading from the underlying reader
+		Incomplete {
+			display("requested data is not buffered yet")
+		}
+		/// Buffer is full and is not allowed to grow (see [`BufRefReaderBuilder::fixed()`](struct.BufRefReaderBuilder.html#method.fixed))
+		BufferFull {
+			display("buffer is full and is not allowed to grow")
+		}
+		/// Underlying reader failed earlier, see [`BufRefReaderBuilder::poison_on_error()`](struct.BufRefReaderBuilder.html#method.poison_on_error)
+		Poisoned {
+			display("underlying reader failed earlier")
+		}
+		/// Consumed data cannot be put back as it is no longer buffered, see [`BufRefReader::unread()`](struct.BufRefReader.html#method.unread)
+		CannotUnread {
+			display("consumed data is no longer buffered")
+		}
+		/// Buffer cannot grow any further as its size would not fit into `usize`
+		/// (`MmapBuffer` reports this as [`Error::Buf`](#variant.Buf) instead)
+		CapacityOverflow {
+			display("buffer capacity overflow")
+		}
+		/// Allocator failed to provide memory for the buffer
+		/// (`MmapBuffer` reports this as [`Error::Buf`](#variant.Buf) instead)
+		AllocFailed {
+			display("failed to allocate buffer")
+		}
+		/// Buffer failed to make any room for new data, hence there's no way to read more of it (e.g. buffer of zero capacity)
+		NoProgress {
+			display("buffer failed to make room for new data")
+		}
+		/// Stream ended with data that is not followed by a delimiter, see [`BufRefReaderBuilder::require_final_delim()`](struct.BufRefReaderBuilder.html#method.require_final_delim).
+		/// This data is not consumed, and is still available with e.g. [`BufRefReader::buffered()`](struct.BufRefReader.html#method.buffered).
+		MissingFinalDelimiter {
+			display("data at the end of the stream is not followed by delimiter")
+		}
+		/// Error writing consumed data to the sink, see [`BufRefReader::tee()`](struct.BufRefReader.html#method.tee)
+		Tee(err: io::Error) {
+			display("failed to write consumed data: {}", err)
+		}
+		/// Underlying reader has no data at the position it has already read from, see [`BufRefReaderBuilder::validate_after_seek()`](struct.BufRefReaderBuilder.html#method.validate_after_seek)
+		SourceChanged {
+			display("underlying reader has no data where it used to")
+		}
+		/**
+		Options that were given to the builder make no sense, or contradict each other, see [`BufRefReaderBuilder::build()`](struct.BufRefReaderBuilder.html#method.build).
+
+		Only the first problem is reported, and these are checked for, in this order:
+
+		- [`capacity`](struct.BufRefReaderBuilder.html#method.capacity) is 0,
+		- [`increment`](struct.BufRefReaderBuilder.html#method.increment) is 0,
+		- [`min_read`](struct.BufRefReaderBuilder.html#method.min_read) is 0,
+		- [`record_delimiter`](struct.BufRefReaderBuilder.html#method.record_delimiter) is empty,
+		- `increment` is set for a [fixed](struct.BufRefReaderBuilder.html#method.fixed) buffer,
+		- [`zero_on_drop`](struct.BufRefReaderBuilder.html#method.zero_on_drop) is set for a buffer that does not support it.
+		*/
+		InvalidConfig(reason: &'static str) {
+			display("invalid configuration: {}", reason)
+		}
+		/// Error writing data to the sink, see [`BufRefReader::copy_until()`](struct.BufRefReader.html#method.copy_until)
+		Write(err: io::Error) {
+			display("failed to write data: {}", err)
+		}
+	}
+}
+
+// writes `data`, consumed at `position`, to `sink`, except for the part that was already written there before being unread
+fn tee(sink: &mut dyn Write, teed: &mut u64, position: u64, data: &[u8]) -> Result<(), Error> {
+	let end = position + data.len() as u64;
+	if end <= *teed {
+		return Ok(());
+	}
+	let skip = (*teed - position) as usize;
+	// if sink fails, these bytes are not going to be written again
+	*teed = end;
+	sink.write_all(&data[skip..]).map_err(Error::Tee)
+}
+impl From<AllocError> for Error {
+	fn from(err: AllocError) -> Self {
+		match err {
+			AllocError::CapacityOverflow => Error::CapacityOverflow,
+			AllocError::AllocFailed => Error::AllocFailed,
+		}
+	}
+}
+
+impl<R: Read, B: Buffer> BufRefReader<R, B>
+where Error: From<B::Error>
+{
+	/// Creates buffered reader with default options. Look for [`BufRefReaderBuilder`](struct.BufRefReaderBuilder.html) for tweaks.
+	pub fn new(src: R) -> Result<BufRefReader<R, B>, Error> {
+		BufRefReaderBuilder::new(src)
+			.build()
+	}
+
+	/**
+	Creates buffered reader with default options, except for initial capacity,
+	with its buffer already holding `leftover` bytes that are returned before any data from `src`.
+
+	This is a counterpart to [`into_parts()`](#method.into_parts), e.g. for handing data that is already buffered over to another protocol.
+	Buffer is allocated large enough to hold `leftover`, even if it's larger than `cap`.
+	*/
+	pub fn from_parts(src: R, leftover: &[u8], cap: usize) -> Result<BufRefReader<R, B>, Error> {
+		let mut r: BufRefReader<R, B> = BufRefReaderBuilder::new(src)
+			.capacity(std::cmp::max(cap, leftover.len()))
+			.build()?;
+		r.buf.append_from(leftover)?;
+		Ok(r)
+	}
+
+	/**
+	Unwraps underlying reader, also returning a copy of data that is buffered but not yet consumed.
+
+	See also [`from_parts()`](#method.from_parts).
+	*/
+	pub fn into_parts(self) -> (R, Vec<u8>) {
+		let leftover = self.buf.filled().to_vec();
+		(self.src, leftover)
+	}
+
+	/**
+	Unwraps underlying reader and the buffer, e.g. to reuse the latter with another reader (see [`BufRefReaderBuilder::build_with()`](struct.BufRefReaderBuilder.html#method.build_with))
+	without allocating a new one.
+
+	Buffer might still hold data that is not consumed yet, which is then returned by the next reader first.
+	Consume it beforehand (or use [`into_parts()`](#method.into_parts) instead) if it belongs to `src`.
+	*/
+	pub fn take_buffer(self) -> (R, B) {
+		(self.src, self.buf)
+	}
+
+	/// Current capacity of the underlying buffer.
+	pub fn capacity(&self) -> usize {
+		self.buf.capacity()
+	}
+
+	/**
+	Grow the buffer so that its capacity is at least `new_cap` (or more, if buffer needs to round it up, see [`Buffer::reserve()`](trait.Buffer.html#method.reserve)),
+	keeping all the buffered data, e.g. once it turns out that tokens are much larger than expected.
+
+	Unlike growth that happens once the buffer is full, this never reads from the underlying reader, and is done in one step instead of doubling capacity several times.
+	Does nothing if capacity is already large enough. This also works for buffers of [fixed](struct.BufRefReaderBuilder.html#method.fixed) size,
+	which then never grow past `new_cap`, although [`AutoShrink`](enum.AutoShrink.html) might still take this capacity away once buffer is empty.
+	*/
+	pub fn grow_to(&mut self, new_cap: usize) -> Result<(), Error> {
+		if new_cap > self.buf.capacity() {
+			self.buf.reserve(new_cap - self.buf.len())?;
+		}
+		Ok(())
+	}
+
+	/**
+	Data that is buffered but not consumed yet, i.e. what the following reads are going to return first.
+
+	This never reads from the underlying reader.
+	*/
+	pub fn buffered(&self) -> &[u8] {
+		self.buf.filled()
+	}
+
+	/// Which kind of buffer this reader uses, see [`Buffer::kind()`](trait.Buffer.html#method.kind).
+	pub fn buffer_kind(&self) -> BufferKind {
+		self.buf.kind()
+	}
+
+	/**
+	Internal layout of the underlying buffer, see [`Buffer::layout()`](trait.Buffer.html#method.layout).
+
+	This is only available in tests, or with `debug-internals` feature enabled.
+	*/
+	#[cfg(any(test, feature = "debug-internals"))]
+	pub fn buffer_layout(&self) -> BufferLayout {
+		self.buf.layout()
+	}
+
+	/**
+	Replaces underlying reader with another one, while keeping already allocated buffer.
+
+	Any data from the previous reader that is still buffered is dropped,
+	[`position()`](#method.position) starts over from 0,
+	and the reader is no longer [poisoned](struct.BufRefReaderBuilder.html#method.poison_on_error).
+	*/
+	pub fn reset(&mut self, src: R) {
+		self.src = src;
+		self.restart();
+	}
+
+	// drops buffered data and starts over every per-stream counter, see reset() and seek()
+	fn restart(&mut self) {
+		let len = self.buf.len();
+		self.buf.consume(len);
+		self.position = 0;
+		self.eof = false;
+		self.poisoned = false;
+		self.read_time = Duration::ZERO;
+		self.scan_resume = None;
+		self.last_enlarge = None;
+		self.teed = 0;
+		self.tokens = 0;
+		self.min_token_len = usize::MAX;
+		self.max_token_len = 0;
+	}
+
+	/**
+	Total time spent in `read()` of the underlying reader, including reads that failed.
+
+	This is always zero unless [`time_reads()`](struct.BufRefReaderBuilder.html#method.time_reads) is enabled.
+	Like [`position()`](#method.position), it starts over once reader is [`reset()`](#method.reset) or after a [`seek()`](#method.seek).
+	*/
+	pub fn read_time(&self) -> Duration {
+		self.read_time
+	}
+
+	/**
+	How the buffer made room for more data the last time it ran out of it, see [`Buffer::enlarge()`](trait.Buffer.html#tymethod.enlarge),
+	or `None` if it never did (or not since the last [`reset()`](#method.reset)).
+
+	Lots of [reallocations](enum.EnlargeResult.html#variant.Reallocated) (e.g. with long tokens) suggest that initial [capacity](struct.BufRefReaderBuilder.html#method.capacity) is too small,
+	while [compaction](enum.EnlargeResult.html#variant.Compacted) is relatively cheap.
+	Buffer that grows by fixed [increment](struct.BufRefReaderBuilder.html#method.increment) is always reallocated once it is full.
+	*/
+	pub fn last_enlarge(&self) -> Option<EnlargeResult> {
+		self.last_enlarge
+	}
+
+	/**
+	Write every byte that is consumed from now on (i.e. returned by any of reading methods, or skipped, e.g. by [`skip_bom()`](#method.skip_bom)) to `sink`,
+	e.g. to save exactly what parser has processed for replaying it later.
+
+	Data that is only buffered (e.g. [peeked](#method.peek_until) at) is not written until it is consumed,
+	and data that is [unread](#method.unread) is not written again once it is consumed for the second time.
+	[`read_logical_line()`](#method.read_logical_line) writes physical lines, as they were in the stream.
+
+	If `sink` fails, read that consumed the data returns [`Error::Tee`](enum.Error.html#variant.Tee), and that data is not written again;
+	the reader itself is still usable.
+	*/
+	pub fn tee<W: Write + Send + 'static>(mut self, sink: W) -> Self {
+		self.teed = self.position;
+		self.tee = Some(Box::new(sink));
+		self
+	}
+
+	/**
+	Amount of bytes returned (consumed) by this reader so far, i.e. position of the next returned byte within the stream.
+
+	For position within the underlying reader, see [`stream_position()`](#method.stream_position).
+	*/
+	pub fn position(&self) -> u64 {
+		self.position
+	}
+
+	/**
+	Statistics of tokens returned so far, e.g. to spot unexpectedly long lines in the input. See [`Stats`](struct.Stats.html) for what counts as a token.
+
+	Like [`position()`](#method.position), these start over once reader is [`reset()`](#method.reset).
+	[Unread](#method.unread) tokens are counted again once they are read for the second time.
+	*/
+	pub fn stats(&self) -> Stats {
+		Stats {
+			bytes: self.position,
+			tokens: self.tokens,
+			min_token_len: self.min_token_len(),
+			max_token_len: self.max_token_len(),
+		}
+	}
+
+	/// Length of the shortest token returned so far, see [`stats()`](#method.stats).
+	pub fn min_token_len(&self) -> Option<usize> {
+		if self.tokens == 0 { None } else { Some(self.min_token_len) }
+	}
+
+	/// Length of the longest token returned so far, see [`stats()`](#method.stats).
+	pub fn max_token_len(&self) -> Option<usize> {
+		if self.tokens == 0 { None } else { Some(self.max_token_len) }
+	}
+
+	#[inline]
+	fn count_token(&mut self, len: usize) {
+		self.tokens += 1;
+		self.min_token_len = std::cmp::min(self.min_token_len, len);
+		self.max_token_len = std::cmp::max(self.max_token_len, len);
+	}
+
+	// consumes a token, see stats()
+	#[inline]
+	fn consume(&mut self, amount: usize) -> Result<&[u8], Error> {
+		self.count_token(std::cmp::min(amount, self.buf.len()));
+		self.skip(amount)
+	}
+
+	// consumes data that is not a token by itself (e.g. BOM)
+	#[inline]
+	fn skip(&mut self, amount: usize) -> Result<&[u8], Error> {
+		let output = self.buf.consume(amount);
+		let position = self.position;
+		self.position += output.len() as u64;
+		if let Some(sink) = self.tee.as_mut() {
+			tee(sink, &mut self.teed, position, output)?;
+		}
+		Ok(output)
+	}
+
+	#[inline]
+	fn consume_and_peek(&mut self, amount: usize, peek: usize) -> Result<&[u8], Error> {
+		let amount = std::cmp::min(amount, self.buf.len());
+		self.count_token(amount);
+		let output = self.buf.consume_and_peek(amount, peek);
+		let position = self.position;
+		self.position += amount as u64;
+		if let Some(sink) = self.tee.as_mut() {
+			tee(sink, &mut self.teed, position, &output[..amount])?;
+		}
+		Ok(output)
+	}
+
+	// returns Some(where appended data starts within the filled part of the buffer),
+	// or None for EOF
+	#[inline]
+	fn fill(&mut self) -> Result<Option<usize>, Error> {
+		if self.poisoned {
+			return Err(Error::Poisoned);
+		}
+
+		if let AutoShrink::Below(threshold) = self.config.auto_shrink {
+			if self.buf.is_empty() && self.buf.capacity() > threshold {
+				self.buf.shrink(self.config.capacity)?;
+			}
+		}
+
+		// enlarge() is a no-op while there's some room left,
+		// but checking that here first saves a call per fill for lots of short tokens
+		if self.buf.appendable().len() < self.config.min_read {
+			let len = self.buf.len();
+			let capacity = self.buf.capacity();
+			if self.config.fixed && len == capacity {
+				// the only way for buffer to make room for more data is to grow
+				return Err(Error::BufferFull);
+			}
+			let result = match self.config.increment {
+				Some(incr) if len == capacity => {
+					self.buf.reserve(incr)?;
+					EnlargeResult::Reallocated
+				},
+				// enlarge() only makes room once there's none left, so ask for the exact amount instead;
+				// full buffer is still doubled as usual, so long tokens do not grow it by `min_read` bytes at a time
+				_ if self.config.min_read > 1 && len < capacity && !(self.config.fixed && capacity - len < self.config.min_read) => {
+					self.buf.reserve(self.config.min_read)?;
+					if self.buf.capacity() == capacity {
+						EnlargeResult::Compacted
+					} else {
+						EnlargeResult::Reallocated
+					}
+				},
+				_ => self.buf.enlarge()?,
+			};
+			self.last_enlarge = Some(result);
+			if self.buf.appendable().is_empty() {
+				// reading into empty slice yields 0, which is indistinguishable from EOF,
+				// and calling this again is not going to change anything either
+				return Err(Error::NoProgress);
+			}
+		}
+
+		// callers resume their searches from this offset within `filled()`;
+		// enlarge() might have moved data within the buffer (e.g. VecBuffer compacts it), but never changes `filled()` itself,
+		// so bytes that were already scanned are not scanned again
+		let old_len = self.buf.len();
+
+		let read = loop {
+			let read = if self.config.time_reads {
+				let started = Instant::now();
+				let read = self.src.read(self.buf.appendable());
+				self.read_time += started.elapsed();
+				read
+			} else {
+				self.src.read(self.buf.appendable())
+			};
+			match read {
+				Err(ref e) if e.kind() == io::ErrorKind::Interrupted && self.config.retry_interrupted => continue,
+				Err(e) => {
+					self.poisoned = self.config.poison_on_error;
+					return Err(e.into());
+				},
+				Ok(read) => break read,
+			}
+		};
+		match read {
+			0 => { // EOF
+				self.eof = true;
+				Ok(None)
+			},
+			n => {
+				self.eof = false;
+				self.buf.mark_appended(n);
+				Ok(Some(old_len))
+			}
+		}
+	}
+
+	// make sure the next fill() is able to grow the buffer `readahead` times
+	fn read_ahead(&mut self) -> Result<(), Error> {
+		if self.config.readahead <= 1 || self.config.fixed {
+			return Ok(());
+		}
+		let room = self.buf.len().saturating_mul(self.config.readahead - 1);
+		while self.buf.appendable().len() < room {
+			let before = self.buf.appendable().len();
+			self.buf.grow()?;
+			if self.buf.appendable().len() <= before {
+				// let fill() deal with this
+				break;
+			}
+		}
+		Ok(())
+	}
+
+	/**
+	Returns requested amount of bytes, or less if EOF prevents reader from fulfilling the request.
+
+	Returned slice is always contiguous, even if data wraps around the end of a ring buffer like [`MmapBuffer`](struct.MmapBuffer.html):
+	every [`Buffer`](trait.Buffer.html) has to provide its [`filled()`](trait.Buffer.html#tymethod.filled) part as a single slice,
+	and `MmapBuffer` does so by mirroring its memory map rather than copying data on every read.
+
+	`read(0)` always returns `Ok(Some(&[]))`, and never reads from the underlying reader, even if nothing is buffered (or past EOF).
+	Likewise, if at least `n` bytes are already buffered, they are returned right away:
+	neither the underlying reader nor the buffer's memory management is involved,
+	so serving data from a warm buffer is as cheap as slicing it (see `bufref_read_buffered_*` benchmarks).
+
+	Returns:
+
+	- `Ok(Some(data))` with, well, data,
+	- `Ok(None)` if no more data is available,
+	- `Err(err)`: see `std::io::Read::read()`
+	*/
+	#[inline]
+	pub fn read(&mut self, n: usize) -> Result<Option<&[u8]>, Error> {
+		if n == 0 {
+			return Ok(Some(&[]));
+		}
+		let mut len = self.buf.len();
+		if n <= len {
+			// fast path
+			return self.consume(n).map(Some);
+		}
+		while n > len {
+			// fill and expand buffer until either:
+			// - buffer starts holding the requested amount of data
+			// - EOF is reached
+			if self.fill()?.is_none() { break };
+			len = self.buf.len();
+		}
+		if len == 0 {
+			// reading past EOF
+			Ok(None)
+		} else {
+			let output = self.consume(n)?;
+			Ok(Some(output))
+		}
+	}
+
+	/**
+	Reads frame length as 32-bit little-endian integer, and returns [`FrameReader`](struct.FrameReader.html)
+	that reads no more than that many bytes that follow it.
+
+	Returns:
+
+	- `Ok(Some(frame))` with, well, the frame,
+	- `Ok(None)` if no more data is available,
+	- `Err(err)`: see `std::io::Read::read()`; this also includes `ErrorKind::UnexpectedEof` if the length itself is cut short by EOF
+	*/
+	pub fn read_frame_u32_le(&mut self) -> Result<Option<FrameReader<'_, R, B>>, Error> {
+		let len = match self.read(4)? {
+			None => return Ok(None),
+			Some(&[a, b, c, d]) => u32::from_le_bytes([a, b, c, d]),
+			Some(_) => return Err(io::Error::from(io::ErrorKind::UnexpectedEof).into()),
+		};
+		Ok(Some(FrameReader::new(self, len as usize)))
+	}
+
+	/**
+	Reads `size_of::<T>()` bytes and decodes them into `T`.
+
+	Bytes are copied as is, so all the multi-byte fields of `T` are expected to be in native byte order;
+	use types like `[u8; 4]` and `u32::from_le_bytes()` if data has some fixed byte order instead.
+
+	This is only available with `bytemuck` feature enabled.
+
+	Returns:
+
+	- `Ok(Some(value))` with, well, decoded value,
+	- `Ok(None)` if no more data is available,
+	- `Err(err)`: see `std::io::Read::read()`; this also includes `ErrorKind::UnexpectedEof` if the value is cut short by EOF
+	*/
+	#[cfg(feature = "bytemuck")]
+	pub fn read_pod<T: bytemuck::Pod>(&mut self) -> Result<Option<T>, Error> {
+		let size = std::mem::size_of::<T>();
+		match self.read(size)? {
+			None => Ok(None),
+			Some(data) if data.len() == size => Ok(Some(bytemuck::pod_read_unaligned(data))),
+			Some(_) => Err(io::Error::from(io::ErrorKind::UnexpectedEof).into()),
+		}
+	}
+
+	/**
+	Reads exactly `N` bytes into an array, e.g. for fixed-size headers that have to outlive the next read without any allocations.
+
+	Returns:
+
+	- `Ok(Some(array))` with, well, data,
+	- `Ok(None)` if no more data is available,
+	- `Err(err)`: see `std::io::Read::read()`; this also includes `ErrorKind::UnexpectedEof` if data is cut short by EOF
+	*/
+	pub fn read_into_array<const N: usize>(&mut self) -> Result<Option<[u8; N]>, Error> {
+		match self.read(N)? {
+			None => Ok(None),
+			Some(data) if data.len() == N => {
+				let mut array = [0; N];
+				array.copy_from_slice(data);
+				Ok(Some(array))
+			},
+			Some(_) => Err(io::Error::from(io::ErrorKind::UnexpectedEof).into()),
+		}
+	}
+
+	/**
+	Consumes byte order mark of UTF-8 or UTF-16, if the data that is not consumed yet begins with one, and tells which one it was.
+	Nothing is consumed if there's no byte order mark.
+
+	This is meant to be called before reading anything else, as byte order mark is only ever expected at the beginning of the stream.
+	*/
+	pub fn skip_bom(&mut self) -> Result<Option<Bom>, Error> {
+		self.prefetch(3)?;
+		let (bom, len) = match self.buf.filled() {
+			[0xef, 0xbb, 0xbf, ..] => (Bom::Utf8, 3),
+			[0xff, 0xfe, ..] => (Bom::Utf16Le, 2),
+			[0xfe, 0xff, ..] => (Bom::Utf16Be, 2),
+			_ => return Ok(None),
+		};
+		self.skip(len)?;
+		Ok(Some(bom))
+	}
+
+	/**
+	Returns up to `max` bytes of whatever is already buffered, or, if buffer is empty, of whatever a single read from the underlying reader returns.
+
+	Unlike [`read()`](#method.read), this never waits for more data than that, and never grows the buffer to fit `max` bytes,
+	so it might return less than requested even if EOF is not reached yet (just like `std::io::Read::read()` does).
+
+	This makes it a building block for streaming parsers (e.g. SIMD tokenizers) that take input in chunks of arbitrary size,
+	as chunks are returned straight from the buffer, and together they cover the whole stream:
+
+	```
+	use buf_ref_reader::*;
+
+	# fn main() -> Result<(), Error> {
+	let data = b"lorem ipsum dolor sit amet";
+	let mut r = BufRefReaderBuilder::new(&data[..])
+		.capacity(8)
+		.build::<VecBuffer>()?;
+	let mut tokenized = vec![];
+	while let Some(chunk) = r.read_some(5)? {
+		assert!(chunk.len() <= 5);
+		tokenized.extend_from_slice(chunk);
+	}
+	assert_eq!(tokenized, data);
+	# Ok(())
+	# }
+	```
+
+	Returns:
+
+	- `Ok(Some(data))` with, well, data,
+	- `Ok(None)` if no more data is available,
+	- `Err(err)`: see `std::io::Read::read()`
+	*/
+	#[inline]
+	pub fn read_some(&mut self, max: usize) -> Result<Option<&[u8]>, Error> {
+		if self.buf.is_empty() && self.fill()?.is_none() {
+			// reading past EOF
+			return Ok(None);
+		}
+		Ok(Some(self.consume(max)?))
+	}
+
+	/**
+	Returns bytes up until and including `delim`, or until EOF mark. If no content is available, returns `None`.
+
+	`delim` is looked up with `memchr`, which uses SIMD instructions where available,
+	and every byte is scanned only once, even if the buffer has to be refilled or reallocated in the middle of the token.
+	Long tokens are scanned at several GB/s, while for short ones (like lines of `/usr/share/dict/words`) overhead of each call dominates
+	(see `bufref_read_until_*` and `bufref_read_until_long_*` benchmarks).
+
+	Underlying reader is only read from if `delim` is not buffered yet,
+	so if it fails right after delivering some data, every complete token in that data is still returned first,
+	and the error only surfaces once there are none left.
+	Incomplete token is not lost either: it remains buffered, and is returned once (and if) underlying reader recovers.
+
+	Returns:
+
+	- `Ok(Some(data))` with, well, data,
+	- `Ok(None)` if no more data is available,
+	- `Err(Error::MissingFinalDelimiter)` if the last token lacks `delim`
+	  and [`require_final_delim()`](struct.BufRefReaderBuilder.html#method.require_final_delim) is set,
+	- `Err(err)`: see `std::io::Read::read()`
+	*/
+	#[inline]
+	pub fn read_until(&mut self, delim: u8) -> Result<Option<&[u8]>, Error> {
+		match self.delim_pos(delim)? {
+			None => { // EOF
+				let len = self.buf.len();
+				if len == 0 {
+					Ok(None)
+				} else if self.config.require_final_delim {
+					Err(Error::MissingFinalDelimiter)
+				} else {
+					let output = self.consume(len)?;
+					Ok(Some(output))
+				}
+			},
+			Some(len) => {
+				let len = len + 1; // also include matching delimiter
+				let output = self.consume(len)?;
+				Ok(Some(output))
+			},
+		}
+	}
+
+	/// Same as [`read_until()`](#method.read_until), but returns [`ReadResult`](enum.ReadResult.html).
+	#[inline]
+	pub fn read_until_flat(&mut self, delim: u8) -> ReadResult<'_> {
+		self.read_until(delim).into()
+	}
+
+	/**
+	Same as [`read_until()`](#method.read_until), but gives up after scanning `budget` bytes without finding `delim`,
+	so that caller can do something else (e.g. yield to other tasks) and call this again later.
+	Scan then resumes where it was left off, as long as nothing else is consumed in the meantime.
+
+	Only scanning counts towards the budget, reading more data into the buffer does not.
+	`budget` must be larger than 0.
+
+	Returns:
+
+	- `Ok(ScanState::Token(data))` with, well, data,
+	- `Ok(ScanState::Pending)` if `delim` is not found within `budget` bytes,
+	- `Ok(ScanState::Eof)` if no more data is available,
+	- `Err(err)`: see `std::io::Read::read()`
+	*/
+	pub fn read_until_budgeted(&mut self, delim: u8, budget: usize) -> Result<ScanState<'_>, Error> {
+		assert!(budget > 0, "budget must be larger than 0");
+
+		// position within filled part of the buffer,
+		// from which to continue search for character
+		let mut pos = match self.scan_resume.take() {
+			Some((d, position, pos)) if d == delim && position == self.position => pos,
+			_ => 0,
+		};
+		let mut budget = budget;
+		let len = loop {
+			let filled = self.buf.filled();
+			let end = std::cmp::min(filled.len(), pos.saturating_add(budget));
+			if let Some(n) = memchr(delim, &filled[pos..end]) {
+				break pos+n+1;
+			}
+			budget -= end - pos;
+			pos = end;
+			if budget == 0 {
+				self.scan_resume = Some((delim, self.position, pos));
+				return Ok(ScanState::Pending);
+			}
+			if self.fill()?.is_none() {
+				break self.buf.len(); // EOF
+			}
+		};
+
+		if len == 0 {
+			Ok(ScanState::Eof)
+		} else {
+			Ok(ScanState::Token(self.consume(len)?))
+		}
+	}
+
+	/**
+	Same as [`read_until()`](#method.read_until), but also returns whatever is buffered past the returned token
+	(see [`buffered()`](#method.buffered)), e.g. to dispatch tokens that are already buffered in a batch.
+
+	Returns:
+
+	- `Ok(Some((data, rest)))` with, well, data,
+	- `Ok(None)` if no more data is available,
+	- `Err(err)`: see `std::io::Read::read()`
+	*/
+	#[inline]
+	pub fn read_until_and_rest(&mut self, delim: u8) -> Result<Option<TokenAndRest<'_>>, Error> {
+		let len = match self.delim_pos(delim)? {
+			None => self.buf.len(), // EOF
+			Some(len) => len + 1, // also include matching delimiter
+		};
+		if len == 0 {
+			Ok(None)
+		} else {
+			// rest is not consumed, and is returned as is
+			let output = self.consume_and_peek(len, usize::MAX)?;
+			Ok(Some(output.split_at(len)))
+		}
+	}
+
+	/**
+	Returns bytes up until and including `n`-th occurrence of `delim`, or until EOF mark, if there are fewer delimiters left.
+	If no content is available, returns `None`.
+
+	This allows processing a batch of tokens (e.g. lines) in a single slice,
+	with data being scanned only once.
+
+	`n` must be larger than 0.
+
+	Returns:
+
+	- `Ok(Some(data))` with, well, data,
+	- `Ok(None)` if no more data is available,
+	- `Err(err)`: see `std::io::Read::read()`
+	*/
+	#[inline]
+	pub fn read_until_nth(&mut self, delim: u8, n: usize) -> Result<Option<&[u8]>, Error> {
+		assert!(n > 0, "n must be larger than 0");
+
+		// position within filled part of the buffer,
+		// from which to continue search for character
+		let mut pos = 0;
+		// how many delimiters were found so far
+		let mut found = 0;
+		let len = 'scan: loop {
+			for i in memchr_iter(delim, &self.buf.filled()[pos..]) {
+				found += 1;
+				if found == n {
+					break 'scan pos+i+1;
+				}
+			}
+			pos = match self.fill()? {
+				None => break self.buf.len(), // EOF
+				Some(pos) => pos,
+			};
+		};
+
+		if len == 0 {
+			Ok(None)
+		} else {
+			Ok(Some(self.consume(len)?))
+		}
+	}
+
+	/**
+	Returns at least `target` bytes, extended up until and including the next `delim`, or until EOF mark.
+	If no content is available, returns `None`.
+
+	This is for processing input in batches of roughly the same size (e.g. to hand them over to worker threads)
+	without splitting records (e.g. lines) across batches: every batch but the last one ends right after `delim`.
+
+	Returns:
+
+	- `Ok(Some(data))` with, well, data,
+	- `Ok(None)` if no more data is available,
+	- `Err(err)`: see `std::io::Read::read()`
+	*/
+	#[inline]
+	pub fn read_batch(&mut self, delim: u8, target: usize) -> Result<Option<&[u8]>, Error> {
+		// delimiter that ends the batch is the first one that is no closer to the beginning than this
+		let from = target.saturating_sub(1);
+		// position within filled part of the buffer,
+		// from which to continue search for character
+		let mut pos = 0;
+		let len = loop {
+			let filled = self.buf.filled();
+			let start = std::cmp::max(pos, from);
+			if start < filled.len() {
+				if let Some(n) = memchr(delim, &filled[start..]) {
+					break start+n+1;
+				}
+			}
+			pos = match self.fill()? {
+				None => break self.buf.len(), // EOF
+				Some(pos) => pos,
+			};
+		};
+
+		if len == 0 {
+			Ok(None)
+		} else {
+			Ok(Some(self.consume(len)?))
+		}
+	}
+
+	/**
+	Same as [`read_until_nth()`](#method.read_until_nth), but works with buffers of fixed size too (see [`BufRefReaderBuilder::fixed()`](struct.BufRefReaderBuilder.html#method.fixed)),
+	which is useful when a token (e.g. a quoted field) spans several delimiters and has to be processed as a whole.
+
+	Tokens are not consumed until all `count` of them are found,
+	so they stay adjacent in the buffer no matter how it is compacted or reallocated in the meantime,
+	and are returned as a single borrowed slice.
+	The only exception is a fixed buffer that cannot hold all of them at once:
+	in that case, whatever is buffered is moved to an owned `Vec` to make room for the rest of the tokens.
+
+	Either way, all `count` tokens count as a single one in [`stats()`](#method.stats).
+
+	`count` must be larger than 0.
+
+	Returns:
+
+	- `Ok(Some(data))` with, well, data,
+	- `Ok(None)` if no more data is available,
+	- `Err(err)`: see `std::io::Read::read()`
+	*/
+	pub fn read_until_joined(&mut self, delim: u8, count: usize) -> Result<Option<Cow<'_, [u8]>>, Error> {
+		assert!(count > 0, "count must be larger than 0");
+
+		// position within filled part of the buffer,
+		// from which to continue search for character
+		let mut pos = 0;
+		// how many delimiters were found so far
+		let mut found = 0;
+		// data that had to be consumed before the last token was found
+		let mut owned = Vec::new();
+		let len = 'scan: loop {
+			for i in memchr_iter(delim, &self.buf.filled()[pos..]) {
+				found += 1;
+				if found == count {
+					break 'scan pos+i+1;
+				}
+			}
+			if self.config.fixed && self.buf.len() == self.buf.capacity() {
+				// everything that's buffered is a part of the output anyway
+				let len = self.buf.len();
+				owned.extend_from_slice(self.skip(len)?);
+			}
+			pos = match self.fill()? {
+				None => break self.buf.len(), // EOF
+				Some(pos) => pos,
+			};
+		};
+
+		if owned.is_empty() {
+			if len == 0 {
+				Ok(None)
+			} else {
+				Ok(Some(Cow::Borrowed(self.consume(len)?)))
+			}
+		} else {
+			owned.extend_from_slice(self.skip(len)?);
+			self.count_token(owned.len());
+			Ok(Some(Cow::Owned(owned)))
+		}
+	}
+
+	/**
+	Same as [`read_until()`](#method.read_until), but the token is wrapped into a [`TokenGuard`](struct.TokenGuard.html),
+	which makes it explicit for how long the reader stays borrowed.
+
+	This is a common pattern for multi-step parsers: fetch a token, extract everything that's needed from it, drop it, fetch the next one.
+
+	```
+	use buf_ref_reader::*;
+
+	# fn main() -> Result<(), Error> {
+	let mut r = BufRefReader::<_, VecBuffer>::new(&b"width=3;height=4;"[..])?;
+	let mut area = 1;
+	while let Some(token) = r.next_token(b';')? {
+		let value = token.iter().position(|&c| c == b'=').map(|i| &token[(i+1)..(token.len()-1)]);
+		area *= std::str::from_utf8(value.unwrap()).unwrap().parse::<u32>().unwrap();
+		// `token` is released here, which allows the next call to next_token()
+	}
+	assert_eq!(area, 12);
+	# Ok(())
+	# }
+	```
+
+	Returns:
+
+	- `Ok(Some(token))` with, well, data,
+	- `Ok(None)` if no more data is available,
+	- `Err(err)`: see `std::io::Read::read()`
+	*/
+	#[inline]
+	pub fn next_token(&mut self, delim: u8) -> Result<Option<TokenGuard<'_>>, Error> {
+		Ok(self.read_until(delim)?.map(|data| TokenGuard { data }))
+	}
+
+	/**
+	Same as [`read_until()`](#method.read_until), but `delim` is a sequence of bytes rather than a single byte.
+
+	If `delim` is the same as the one set with [`BufRefReaderBuilder::record_delimiter()`](struct.BufRefReaderBuilder.html#method.record_delimiter),
+	searcher that was built for it is reused, otherwise it is built anew with every call, which might be quite wasteful for short tokens.
+
+	`delim` must not be empty.
+
+	Returns:
+
+	- `Ok(Some(data))` with, well, data,
+	- `Ok(None)` if no more data is available,
+	- `Err(err)`: see `std::io::Read::read()`
+	*/
+	#[inline]
+	pub fn read_until_bytes(&mut self, delim: &[u8]) -> Result<Option<&[u8]>, Error> {
+		assert!(!delim.is_empty(), "delimiter must not be empty");
+		let len = match self.finder.take() {
+			Some(finder) if finder.needle() == delim => {
+				let len = self.find_len(&finder);
+				self.finder = Some(finder);
+				len
+			},
+			finder => {
+				self.finder = finder;
+				self.find_len(&Finder::new(delim))
+			},
+		}?;
+		self.read_found(len)
+	}
+
+	/**
+	Same as [`read_until_bytes()`](#method.read_until_bytes) with delimiter that was set with [`BufRefReaderBuilder::record_delimiter()`](struct.BufRefReaderBuilder.html#method.record_delimiter),
+	the searcher for which is only built once.
+
+	Panics if record delimiter was not set.
+	*/
+	#[inline]
+	pub fn read_record(&mut self) -> Result<Option<&[u8]>, Error> {
+		let finder = self.finder.take()
+			.expect("record delimiter is not set");
+		let len = self.find_len(&finder);
+		self.finder = Some(finder);
+		let len = len?;
+		self.read_found(len)
+	}
+
+	// returns length of data up until and including delimiter, or None for EOF
+	#[inline]
+	fn find_len(&mut self, finder: &Finder) -> Result<Option<usize>, Error> {
+		let n = finder.needle().len();
+		// position within filled part of the buffer,
+		// from which to continue search for delimiter
+		let mut pos = 0;
+		loop {
+			if let Some(i) = finder.find(&self.buf.filled()[pos..]) {
+				return Ok(Some(pos+i+n));
+			}
+			pos = match self.fill()? {
+				None => return Ok(None), // EOF
+				// delimiter might've been cut short by the end of previously buffered data
+				Some(pos) => pos.saturating_sub(n-1),
+			};
+		}
+	}
+
+	// consumes token found by find_len()
+	#[inline]
+	fn read_found(&mut self, len: Option<usize>) -> Result<Option<&[u8]>, Error> {
+		match len {
+			None => { // EOF
+				if self.buf.len() == 0 {
+					Ok(None)
+				} else {
+					let output = self.consume(self.buf.len())?;
+					Ok(Some(output))
+				}
+			},
+			Some(len) => Ok(Some(self.consume(len)?)),
+		}
+	}
+
+	/**
+	Returns bytes up until and including the earliest occurrence of any of `needles`, or until EOF mark,
+	along with the index of the needle that matched (or `None` at EOF). If no content is available, returns `None`.
+
+	If several needles occur at the same position (e.g. `->` and `-->`), the longest one is matched.
+	To tell which one it is, this might wait for more data than the shortest of them needs.
+
+	Needles must not be empty.
+
+	Returns:
+
+	- `Ok(Some((data, needle)))` with, well, data,
+	- `Ok(None)` if no more data is available,
+	- `Err(err)`: see `std::io::Read::read()`
+	*/
+	pub fn read_until_any_seq(&mut self, needles: &[&[u8]]) -> Result<Option<SeqToken<'_>>, Error> {
+		assert!(needles.iter().all(|needle| !needle.is_empty()), "needles must not be empty");
+		let longest = needles.iter().map(|needle| needle.len()).max().unwrap_or(0);
+
+		// position within filled part of the buffer,
+		// from which to continue search for needles
+		let mut pos = 0;
+		let mut eof = false;
+		let (len, found) = loop {
+			let filled = self.buf.filled();
+			// earliest match: where it begins, and which needle it is
+			let mut best: Option<(usize, usize)> = None;
+			for (i, needle) in needles.iter().enumerate() {
+				if let Some(n) = memmem::find(&filled[pos..], needle) {
+					let start = pos + n;
+					best = match best {
+						Some((s, j)) if s < start || (s == start && needles[j].len() >= needle.len()) => Some((s, j)),
+						_ => Some((start, i)),
+					};
+				}
+			}
+			match best {
+				Some((start, i)) if eof || start + longest <= filled.len() => break (start + needles[i].len(), Some(i)),
+				// some other needle that begins no later than this one might still be cut short by the end of buffered data
+				Some(_) => (),
+				None if eof => break (filled.len(), None),
+				// any of needles might be cut short by the end of buffered data
+				None => pos = filled.len().saturating_sub(longest.saturating_sub(1)),
+			}
+			if self.fill()?.is_none() {
+				eof = true;
+			}
+		};
+
+		if len == 0 {
+			Ok(None)
+		} else {
+			Ok(Some((self.consume(len)?, found)))
+		}
+	}
+
+	/**
+	Reads data from the underlying reader until `delim` is buffered, without consuming anything,
+	and returns its offset from the current [`position()`](#method.position)
+	(i.e. the next [`read_until()`](#method.read_until) would return `pos + 1` bytes).
+
+	Returns `Ok(None)` if EOF is reached before `delim` is found.
+	*/
+	#[inline]
+	pub fn delim_pos(&mut self, delim: u8) -> Result<Option<usize>, Error> {
+		// position within filled part of the buffer,
+		// from which to continue search for character
+		let mut pos = 0;
+		// whether some data was already read in search of `delim`
+		let mut searched = false;
+		loop {
+			// fill and expand buffer until either:
+			// - `delim` appears in the buffer
+			// - EOF is reached
+			if let Some(n) = memchr(delim, &self.buf.filled()[pos..]) {
+				return Ok(Some(pos+n));
+			}
+			if searched {
+				self.read_ahead()?;
+			}
+			searched = true;
+			pos = match self.fill()? {
+				None => return Ok(None), // EOF
+				Some(pos) => pos,
+			};
+		}
+	}
+
+	/**
+	Same as [`read_until()`](#method.read_until), but does not consume anything,
+	so the next `read_until()` with the same `delim` returns the same bytes without reading any more data.
+
+	Returns:
+
+	- `Ok(Some(data))` with, well, data,
+	- `Ok(None)` if no more data is available,
+	- `Err(err)`: see `std::io::Read::read()`
+	*/
+	#[inline]
+	pub fn peek_until(&mut self, delim: u8) -> Result<Option<&[u8]>, Error> {
+		let len = match self.delim_pos(delim)? {
+			None => self.buf.len(), // EOF
+			Some(len) => len + 1, // also include matching delimiter
+		};
+		if len == 0 {
+			Ok(None)
+		} else {
+			Ok(Some(&self.buf.filled()[..len]))
+		}
+	}
+
+	/**
+	Writes bytes up until and including `delim`, or until EOF mark, to `sink`, and returns how many of them were written.
+
+	This is the same as writing whatever [`read_until()`](#method.read_until) returns, e.g. for filters that only pass some of the lines through.
+	Token is only consumed once `sink` accepts all of it, so if writing fails, the next call starts with the same token again
+	(although `sink` might have already received some part of it).
+
+	Returns:
+
+	- `Ok(Some(len))` with, well, the length of the token,
+	- `Ok(None)` if no more data is available,
+	- `Err(Error::Write(err))` if `sink` failed,
+	- `Err(err)`: see `std::io::Read::read()`
+	*/
+	pub fn copy_until<W: Write + ?Sized>(&mut self, delim: u8, sink: &mut W) -> Result<Option<usize>, Error> {
+		let len = match self.delim_pos(delim)? {
+			None => self.buf.len(), // EOF
+			Some(len) => len + 1, // also include matching delimiter
+		};
+		if len == 0 {
+			return Ok(None);
+		}
+		sink.write_all(&self.buf.filled()[..len]).map_err(Error::Write)?;
+		self.consume(len)?;
+		Ok(Some(len))
+	}
+
+	/**
+	Consumes the rest of the stream, returning the number of `delim` bytes in it (e.g. to count lines, like `wc -l` does).
+
+	This is much faster than counting tokens returned by [`read_until()`](#method.read_until),
+	as it simply scans every chunk of data as soon as it is buffered, and does not have to grow the buffer to fit long tokens.
+	*/
+	pub fn count_until_eof(&mut self, delim: u8) -> Result<u64, Error> {
+		let mut count = 0;
+		loop {
+			count += memchr_iter(delim, self.buf.filled()).count() as u64;
+			let len = self.buf.len();
+			self.skip(len)?;
+			if self.fill()?.is_none() {
+				return Ok(count);
+			}
+		}
+	}
+
+	/**
+	Reads data from the underlying reader until at least `up_to` bytes are buffered, or until EOF, without consuming anything.
+	Returns the amount of data that is now available in the buffer.
+
+	Use this to warm up the buffer before latency-sensitive part of the code,
+	so that subsequent reads of up to that many bytes do not have to wait for the underlying reader.
+
+	In [fixed](struct.BufRefReaderBuilder.html#method.fixed) mode, prefetching stops once the buffer is full.
+	*/
+	pub fn prefetch(&mut self, up_to: usize) -> Result<usize, Error> {
+		while up_to > self.buf.len() {
+			if self.config.fixed && self.buf.len() == self.buf.capacity() { break };
+			if self.fill()?.is_none() { break };
+		}
+		Ok(self.buf.len())
+	}
+
+	/**
+	Returns up to `max` bytes from the beginning of data that is not consumed yet, e.g. to detect format of the input by its magic bytes,
+	reading from the underlying reader as needed (see [`prefetch()`](#method.prefetch)).
+
+	Nothing is consumed, so whatever parses the input afterwards still gets all of it, including returned bytes.
+	Less than `max` bytes are returned if stream is shorter than that, or if buffer is [fixed](struct.BufRefReaderBuilder.html#method.fixed) and is smaller than that.
+	*/
+	pub fn sniff(&mut self, max: usize) -> Result<&[u8], Error> {
+		let len = std::cmp::min(self.prefetch(max)?, max);
+		Ok(&self.buf.filled()[..len])
+	}
+
+	/**
+	Same as [`read_until()`](#method.read_until), but only looks for `delim` within data that is already buffered,
+	and never reads from the underlying reader.
+
+	This allows caller to decide when it's time to do IO (e.g. with [`prefetch()`](#method.prefetch)).
+
+	Returns:
+
+	- `Ok(Some(data))` with, well, data,
+	- `Ok(None)` if no more data is available, i.e. buffer is empty, and the last read from the underlying reader reported EOF,
+	- `Err(Error::Incomplete)` if `delim` is not buffered yet, and the last read did not report EOF.
+	*/
+	#[inline]
+	pub fn try_read_until(&mut self, delim: u8) -> Result<Option<&[u8]>, Error> {
+		match memchr(delim, self.buf.filled()) {
+			Some(len) => Ok(Some(self.consume(len + 1)?)),
+			None if !self.eof => Err(Error::Incomplete),
+			None if self.buf.is_empty() => Ok(None),
+			None => {
+				let len = self.buf.len();
+				Ok(Some(self.consume(len)?))
+			},
+		}
+	}
+
+	/**
+	Same as [`read_until()`](#method.read_until) with `record_delim`, but also returns offsets of every `field_delim` within returned record,
+	so that fields can be sliced out of it later on, without scanning the record once again.
+
+	Offsets are kept inline for up to 16 fields, hence records that are not any wider than that do not cause any allocations.
+
+	Returns:
+
+	- `Ok(Some((data, offsets)))` with, well, data,
+	- `Ok(None)` if no more data is available,
+	- `Err(err)`: see `std::io::Read::read()`
+	*/
+	#[inline]
+	pub fn read_record_offsets(&mut self, record_delim: u8, field_delim: u8) -> Result<Option<(&[u8], FieldOffsets)>, Error> {
+		Ok(self.read_until(record_delim)?.map(|record| {
+			(record, memchr_iter(field_delim, record).collect())
+		}))
+	}
+
+	/**
+	Same as [`read_until()`](#method.read_until), but instead of the data itself
+	returns its position within the stream (see [`position()`](#method.position)).
+
+	Returned data can be retrieved with [`slice()`](#method.slice)
+	for as long as it's still kept in the buffer.
+	*/
+	#[inline]
+	pub fn read_until_range(&mut self, delim: u8) -> Result<Option<Range<u64>>, Error> {
+		let start = self.position;
+		Ok(self.read_until(delim)?.map(|output| {
+			start .. (start + output.len() as u64)
+		}))
+	}
+
+	/**
+	Puts the last `n` consumed bytes back, so that they are returned once again by subsequent reads,
+	and moves [`position()`](#method.position) back accordingly.
+
+	This only works for as long as these bytes are still held by the buffer (see [`slice()`](#method.slice)),
+	otherwise [`Error::CannotUnread`](enum.Error.html#variant.CannotUnread) is returned, and nothing is put back.
+	Data that was modified in place (e.g. by [`read_logical_line()`](#method.read_logical_line)) is put back as such.
+	*/
+	pub fn unread(&mut self, n: usize) -> Result<(), Error> {
+		let consumed = self.retained().len() - self.buf.len();
+		if n > consumed {
+			return Err(Error::CannotUnread);
+		}
+		self.buf.unconsume(n);
+		self.position -= n as u64;
+		Ok(())
+	}
+
+	// same as Buffer::retained(), except for data that was consumed before reset() (or by another reader, see build_with())
+	fn retained(&self) -> &[u8] {
+		let retained = self.buf.retained();
+		let consumed = retained.len() - self.buf.len();
+		let own = std::cmp::min(consumed as u64, self.position) as usize;
+		&retained[ (consumed - own) .. ]
+	}
+
+	/**
+	Returns part of the stream that was previously returned by [`read_until_range()`](#method.read_until_range)
+	(or any other part of the stream, for that matter).
+
+	This only works while these bytes are still held by the buffer,
+	which is generally up until the buffer is refilled (or even just compacted) by subsequent reads.
+	For data that is no longer available, returns `None`.
+	*/
+	pub fn slice(&self, range: Range<u64>) -> Option<&[u8]> {
+		let retained = self.retained();
+		// stream position of the first byte that is still available
+		let first = self.position - (retained.len() - self.buf.len()) as u64;
+		if range.start < first || range.start > range.end || range.end > first + retained.len() as u64 {
+			return None;
+		}
+		Some(&retained[ (range.start - first) as usize .. (range.end - first) as usize ])
+	}
+
+	/**
+	Skips ASCII whitespace (space, `\t`, `\n`, `\r`, form feed, and vertical tab), then returns the word that follows it,
+	i.e. everything up until the next whitespace byte, or until EOF mark. That whitespace byte is consumed, but is not returned.
+	If no content but whitespace is available, returns `None`.
+
+	This is similar to `split_ascii_whitespace()`: runs of whitespace of any length separate words,
+	and there are no empty words, not even at the beginning or at the end of the stream.
+
+	Returns:
+
+	- `Ok(Some(word))` with, well, data,
+	- `Ok(None)` if no more words are available,
+	- `Err(err)`: see `std::io::Read::read()`
+	*/
+	pub fn read_word(&mut self) -> Result<Option<&[u8]>, Error> {
+		const SPACE: ByteSet = ByteSet::from_bytes(b" \t\n\r\x0c\x0b");
+
+		// skip leading whitespace
+		loop {
+			let filled = self.buf.filled();
+			match filled.iter().position(|&c| !SPACE.contains(c)) {
+				Some(n) => {
+					self.skip(n)?;
+					break;
+				},
+				None => {
+					let len = filled.len();
+					self.skip(len)?;
+					if self.fill()?.is_none() {
+						return Ok(None); // EOF
+					}
+				},
+			}
+		}
+
+		// position within filled part of the buffer,
+		// from which to continue search for whitespace
+		let mut pos = 0;
+		let len = loop {
+			if let Some(n) = self.buf.filled()[pos..].iter().position(|&c| SPACE.contains(c)) {
+				break Some(pos+n);
+			}
+			pos = match self.fill()? {
+				None => break None, // EOF
+				Some(pos) => pos,
+			};
+		};
+
+		match len {
+			None => {
+				let len = self.buf.len();
+				Ok(Some(self.consume(len)?))
+			},
+			// also consume whitespace that follows the word
+			Some(len) => Ok(Some(&self.consume(len + 1)?[..len])),
+		}
+	}
+
+	/**
+	Returns bytes up until and including the first byte that is within `range` (e.g. any ASCII control character with `0..=31`), or until EOF mark.
+	If no content is available, returns `None`.
+
+	Returns:
+
+	- `Ok(Some(data))` with, well, data,
+	- `Ok(None)` if no more data is available,
+	- `Err(err)`: see `std::io::Read::read()`
+	*/
+	pub fn read_until_in_range(&mut self, range: RangeInclusive<u8>) -> Result<Option<&[u8]>, Error> {
+		self.read_until_set(&ByteSet::from_fn(|c| range.contains(&c)))
+	}
+
+	/**
+	Returns bytes up until and including the first byte that is in `set`, or until EOF mark.
+	If no content is available, returns `None`.
+
+	Every byte is checked with a single lookup in [`ByteSet`](struct.ByteSet.html), so this is equally fast for sets of any size,
+	but it is still much slower than [`read_until()`](#method.read_until), which uses SIMD instructions to look for a single delimiter
+	(see `bufref_read_until_set_*` benchmarks).
+
+	Returns:
+
+	- `Ok(Some(data))` with, well, data,
+	- `Ok(None)` if no more data is available,
+	- `Err(err)`: see `std::io::Read::read()`
+	*/
+	pub fn read_until_set(&mut self, set: &ByteSet) -> Result<Option<&[u8]>, Error> {
+		// position within filled part of the buffer,
+		// from which to continue search for delimiter
+		let mut pos = 0;
+		let len = loop {
+			if let Some(n) = self.buf.filled()[pos..].iter().position(|&c| set.contains(c)) {
+				break Some(pos+n);
+			}
+			pos = match self.fill()? {
+				None => break None, // EOF
+				Some(pos) => pos,
+			};
+		};
+
+		match len {
+			None => { // EOF
+				if self.buf.len() == 0 {
+					Ok(None)
+				} else {
+					let output = self.consume(self.buf.len())?;
+					Ok(Some(output))
+				}
+			},
+			Some(len) => {
+				let len = len + 1; // also include matching delimiter
+				let output = self.consume(len)?;
+				Ok(Some(output))
+			},
+		}
+	}
+
+	/**
+	Returns bytes up until and including the first position for which `f` returns `true`, or until EOF mark.
+	If no content is available, returns `None`.
+
+	`f` is given all of the data buffered so far (starting with the token itself) and a candidate position within it,
+	and decides if byte at that position is the actual delimiter, which allows for context-dependent delimiters
+	(e.g. ones that can be escaped by preceding bytes).
+	For each token, `f` is called exactly once for every position in order,
+	so it is also fine for `f` to keep track of its own state (e.g. whether it is within quoted part of the token).
+
+	Unlike [`read_until()`](#method.read_until), this scans data one byte at a time,
+	hence it is about twice as slow for short tokens, and even slower for long ones.
+
+	Returns:
+
+	- `Ok(Some(data))` with, well, data,
+	- `Ok(None)` if no more data is available,
+	- `Err(err)`: see `std::io::Read::read()`
+	*/
+	#[inline]
+	pub fn read_until_fn<F>(&mut self, mut f: F) -> Result<Option<&[u8]>, Error>
+	where F: FnMut(&[u8], usize) -> bool
+	{
+		let mut len = None;
+		// position within filled part of the buffer,
+		// from which to continue calling `f`
+		let mut pos = 0;
+		loop {
+			let filled = self.buf.filled();
+			if let Some(n) = (pos..filled.len()).find(|&i| f(filled, i)) {
+				len = Some(n);
+				break;
+			}
+			pos = match self.fill()? {
+				None => break, // EOF
+				Some(pos) => pos,
+			};
+		}
+
+		match len {
+			None => { // EOF
+				if self.buf.len() == 0 {
+					Ok(None)
+				} else {
+					let output = self.consume(self.buf.len())?;
+					Ok(Some(output))
+				}
+			},
+			Some(len) => {
+				let len = len + 1; // also include matching delimiter
+				let output = self.consume(len)?;
+				Ok(Some(output))
+			},
+		}
+	}
+
+	/**
+	Like [`read_until()`](#method.read_until), but never returns more than `chunk_max` bytes at once,
+	which allows for streaming arbitrarily long tokens without having to buffer them entirely.
+
+	Along with the data, returns a flag that is `true` if returned chunk is not the end of the token yet,
+	i.e. it was cut short at `chunk_max` bytes before `delim` was encountered.
+	The following call then returns the next chunk of the same token.
+
+	`chunk_max` must be larger than 0.
+
+	Returns:
+
+	- `Ok(Some((data, more)))` with, well, data,
+	- `Ok(None)` if no more data is available,
+	- `Err(err)`: see `std::io::Read::read()`
+	*/
+	#[inline]
+	pub fn read_chunked(&mut self, delim: u8, chunk_max: usize) -> Result<Option<(&[u8], bool)>, Error> {
+		assert!(chunk_max > 0, "chunk_max must be larger than 0");
+
+		let mut pos = 0;
+		let (len, more) = loop {
+			let filled = self.buf.filled();
+			let end = std::cmp::min(filled.len(), chunk_max);
+			if let Some(n) = memchr(delim, &filled[pos..end]) {
+				break (pos+n+1, false);
+			}
+			if end == chunk_max {
+				// no need to buffer any more data than that
+				break (chunk_max, true);
+			}
+			pos = end;
+			if self.fill()?.is_none() {
+				// EOF
+				break (self.buf.len(), false);
+			}
+		};
+
+		if len == 0 {
+			Ok(None)
+		} else {
+			Ok(Some((self.consume(len)?, more)))
+		}
+	}
+
+	/**
+	Returns bytes up until and including `delim`, or `max` bytes, whichever comes first,
+	which is the kind of framing protocols with both delimiters and size limits (e.g. Redis inline commands) need.
+
+	Along with the data, returns a flag that is `true` if returned token ends with `delim`.
+	If it does not, the token was either cut short at `max` bytes, with the rest of it left buffered for the following calls,
+	or cut short by EOF.
+
+	This is the same as [`read_chunked()`](#method.read_chunked), except the flag is reversed and EOF is reported as well.
+
+	`max` must be larger than 0.
+
+	Returns:
+
+	- `Ok(Some((data, terminated)))` with, well, data,
+	- `Ok(None)` if no more data is available,
+	- `Err(err)`: see `std::io::Read::read()`
+	*/
+	#[inline]
+	pub fn read_until_or_max(&mut self, delim: u8, max: usize) -> Result<Option<(&[u8], bool)>, Error> {
+		assert!(max > 0, "max must be larger than 0");
+		Ok(self.read_chunked(delim, max)?.map(|(data, _)| (data, data.last() == Some(&delim))))
+	}
+
+	/**
+	Returns `chunk` bytes (or less, if EOF prevents reader from fulfilling the request),
+	with the last `overlap` bytes of returned data being left in the buffer,
+	so that they are also returned at the front of the next chunk.
+
+	This is useful for e.g. searching for patterns that might straddle chunk boundaries.
+	Chunk that ends at EOF keeps no overlap, so no data is returned twice at the end of the stream.
+	To tell if that's the case, the reader needs to look (and, possibly, wait) for at least one byte past the chunk.
+
+	`overlap` must be smaller than `chunk`.
+
+	Returns:
+
+	- `Ok(Some(data))` with, well, data,
+	- `Ok(None)` if no more data is available,
+	- `Err(err)`: see `std::io::Read::read()`
+	*/
+	#[inline]
+	pub fn read_overlapping(&mut self, chunk: usize, overlap: usize) -> Result<Option<&[u8]>, Error> {
+		assert!(overlap < chunk, "overlap must be smaller than chunk");
+
+		// fill and expand buffer until either:
+		// - buffer holds at least one byte past requested chunk
+		// - EOF is reached
+		while chunk >= self.buf.len() {
+			if self.fill()?.is_none() { break };
+		}
+
+		let len = self.buf.len();
+		if len == 0 {
+			// reading past EOF
+			Ok(None)
+		} else if len > chunk {
+			Ok(Some(self.consume_and_peek(chunk - overlap, overlap)?))
+		} else {
+			// last chunk
+			Ok(Some(self.consume(len)?))
+		}
+	}
+
+	/**
+	Returns next line, stripped of its terminator, which is either `\n` or `\r\n`.
+	If no content is available, returns `None`.
+
+	Lone `\r` that is not followed by `\n` is not considered a line terminator
+	and is returned as a part of the line.
+
+	Returns:
+
+	- `Ok(Some(data))` with, well, data,
+	- `Ok(None)` if no more data is available,
+	- `Err(err)`: see `std::io::Read::read()`
+	*/
+	#[inline]
+	pub fn read_line(&mut self) -> Result<Option<&[u8]>, Error> {
+		// the whole line is kept within the buffer until it's returned,
+		// so there's no need to track `\r` across fills: just look behind the `\n` once it's found
+		Ok(self.read_until(b'\n')?.map(|line| {
+			match line {
+				[line @ .., b'\r', b'\n'] => line,
+				[line @ .., b'\n'] => line,
+				line => line, // EOF
+			}
+		}))
+	}
+
+	/**
+	Same as [`read_line()`](#method.read_line), but also tells which terminator the line had,
+	e.g. to write lines back with the same terminators they had originally.
+
+	Returns:
+
+	- `Ok(Some((data, ending)))` with, well, data,
+	- `Ok(None)` if no more data is available,
+	- `Err(err)`: see `std::io::Read::read()`
+	*/
+	#[inline]
+	pub fn read_line_with_ending(&mut self) -> Result<Option<(&[u8], LineEnding)>, Error> {
+		Ok(self.read_until(b'\n')?.map(|line| {
+			match line {
+				[line @ .., b'\r', b'\n'] => (line, LineEnding::CrLf),
+				[line @ .., b'\n'] => (line, LineEnding::Lf),
+				line => (line, LineEnding::None), // EOF
+			}
+		}))
+	}
+
+	/**
+	Same as [`read_line()`](#method.read_line), but returns the line as a string, e.g. for logs that are mostly UTF-8 but might have some garbage in them.
+
+	Valid UTF-8 is returned as is, without copying, while **lines with invalid sequences are copied into a newly allocated `String`**,
+	with each of these sequences replaced by `U+FFFD REPLACEMENT CHARACTER` (see `String::from_utf8_lossy()`).
+	*/
+	#[inline]
+	pub fn read_line_lossy(&mut self) -> Result<Option<Cow<'_, str>>, Error> {
+		Ok(self.read_line()?.map(String::from_utf8_lossy))
+	}
+
+	/**
+	Returns lines up until the next blank line (i.e. the next `\n\n` or `\r\n\r\n`), or until EOF mark, without the line terminator of the last one of them.
+	If no content is available, returns `None`.
+
+	Blank lines themselves are consumed, however many of them there are, and are never returned,
+	so this is what separates paragraphs of text in formats like Markdown.
+
+	Returns:
+
+	- `Ok(Some(data))` with, well, data,
+	- `Ok(None)` if no more data is available,
+	- `Err(err)`: see `std::io::Read::read()`
+	*/
+	pub fn read_paragraph(&mut self) -> Result<Option<&[u8]>, Error> {
+		// blank lines before the paragraph, including those that follow the previous one
+		loop {
+			self.prefetch(2)?;
+			let len = match self.buf.filled() {
+				[b'\n', ..] => 1,
+				[b'\r', b'\n', ..] => 2,
+				_ => break,
+			};
+			self.skip(len)?;
+		}
+
+		// position within filled part of the buffer,
+		// from which to continue search for line terminator
+		let mut pos = 0;
+		// (end of the last line, including its terminator; end of the blank line that follows it)
+		let (text, len) = 'scan: loop {
+			let filled = self.buf.filled();
+			let mut resume = filled.len();
+			for i in memchr_iter(b'\n', &filled[pos..]).map(|i| pos + i) {
+				match &filled[(i+1)..] {
+					[b'\n', ..] => break 'scan (i+1, i+2),
+					[b'\r', b'\n', ..] => break 'scan (i+1, i+3),
+					// it's too early to tell whether the next line is blank
+					[] | [b'\r'] => {
+						resume = i;
+						break;
+					},
+					_ => (),
+				}
+			}
+			pos = resume;
+			if self.fill()?.is_none() {
+				// EOF
+				let len = self.buf.len();
+				break (len, len);
+			}
+		};
+
+		let filled = self.buf.filled();
+		let mut text = text;
+		// strip line terminators, including trailing blank lines right before EOF
+		loop {
+			text -= match &filled[..text] {
+				[.., b'\r', b'\n'] => 2,
+				[.., b'\n'] => 1,
+				_ => break,
+			};
+		}
+
+		if len == 0 {
+			Ok(None)
+		} else {
+			let output = self.consume(len)?;
+			Ok(Some(&output[..text]))
+		}
+	}
+
+	/**
+	Turns this reader into an `Iterator` over lines, stripped of their terminators just like [`read_line()`](#method.read_line) does.
+
+	**This reintroduces an allocation per line**, as every line is copied into its own `Vec`,
+	which is exactly what this crate is meant to avoid.
+	Use it only where convenience of `for line in r.lines_owned()` matters more than performance
+	(see also [`LineReader`](struct.LineReader.html), which at least reuses the same `Vec` for all lines).
+
+	Like `str::split_terminator()` (and `BufRead::lines()`), this does not yield an empty line past the trailing line terminator.
+	*/
+	pub fn lines_owned(mut self) -> impl Iterator<Item = Result<Vec<u8>, Error>> {
+		std::iter::from_fn(move || {
+			self.read_line()
+				.map(|line| line.map(|line| line.to_vec()))
+				.transpose()
+		})
+	}
+
+	/**
+	Turns this reader into an `Iterator` over tokens separated by `delim`, with `delim` itself stripped.
+	Just like `str::split_terminator()`, this does not yield an empty token past the trailing `delim`.
+
+	As with [`lines_owned()`](#method.lines_owned), **every token is copied into a newly allocated `Vec`**.
+	*/
+	pub fn split_terminator_owned(mut self, delim: u8) -> impl Iterator<Item = Result<Vec<u8>, Error>> {
+		std::iter::from_fn(move || {
+			self.read_until(delim)
+				.map(|token| token.map(|token| {
+					token.strip_suffix(&[delim]).unwrap_or(token).to_vec()
+				}))
+				.transpose()
+		})
+	}
+
+	/**
+	Same as [`split_terminator_owned()`](#method.split_terminator_owned), but behaves like `str::split()` instead:
+	if data ends with `delim` (or if there's no data at all), the last token is an empty one.
+
+	As with [`lines_owned()`](#method.lines_owned), **every token is copied into a newly allocated `Vec`**.
+	*/
+	pub fn split_owned(mut self, delim: u8) -> impl Iterator<Item = Result<Vec<u8>, Error>> {
+		// whether there's an empty token to yield at EOF
+		let mut trailing = true;
+		std::iter::from_fn(move || {
+			match self.read_until(delim) {
+				Ok(Some(token)) => {
+					trailing = token.last() == Some(&delim);
+					Some(Ok(token.strip_suffix(&[delim]).unwrap_or(token).to_vec()))
+				},
+				Ok(None) if trailing => {
+					trailing = false;
+					Some(Ok(vec![]))
+				},
+				Ok(None) => None,
+				Err(err) => Some(Err(err)),
+			}
+		})
+	}
+
+	/**
+	Turns this reader into an `Iterator` over records of exactly `N` bytes, as read by [`read_into_array()`](#method.read_into_array).
+
+	Unlike [`lines_owned()`](#method.lines_owned), this does not allocate anything, as records are returned in arrays.
+	If data ends with a partial record, it is consumed, and iterator yields `ErrorKind::UnexpectedEof` error in its place, which is the last item it yields.
+	*/
+	pub fn records<const N: usize>(mut self) -> impl Iterator<Item = Result<[u8; N], Error>> {
+		std::iter::from_fn(move || self.read_into_array::<N>().transpose())
+	}
+
+	/**
+	Returns next logical line, which spans as many physical lines as there are lines that end with `cont` (e.g. `b'\\'`).
+	Physical lines are joined together, with all the `cont` bytes and line terminators in between removed.
+	Line terminators are the same as with [`read_line()`](#method.read_line), and the last one is also stripped.
+	If no content is available, returns `None`.
+
+	Continuation that is followed by EOF is removed as well.
+
+	To join lines, data is moved around in the part of the buffer that is being consumed,
+	hence the returned bytes (and consumed bytes that [`slice()`](#method.slice) returns) might not match the source.
+	[`position()`](#method.position) still advances by the length of all the physical lines.
+
+	Returns:
+
+	- `Ok(Some(data))` with, well, data,
+	- `Ok(None)` if no more data is available,
+	- `Err(err)`: see `std::io::Read::read()`
+	*/
+	pub fn read_logical_line(&mut self, cont: u8) -> Result<Option<&[u8]>, Error> {
+		fn continued(line: &[u8], cont: u8) -> bool {
+			let line = match line {
+				[line @ .., b'\r'] => line,
+				line => line,
+			};
+			line.last() == Some(&cont)
+		}
+
+		// where the last physical line starts within filled part of the buffer
+		let mut line = 0;
+		// position within filled part of the buffer,
+		// from which to continue search for `\n`
+		let mut pos = 0;
+		let len = loop {
+			if let Some(n) = memchr(b'\n', &self.buf.filled()[pos..]) {
+				let nl = pos + n;
+				if !continued(&self.buf.filled()[line..nl], cont) {
+					break nl + 1;
+				}
+				line = nl + 1;
+				pos = nl + 1;
+				continue;
+			}
+			pos = match self.fill()? {
+				None => break self.buf.len(), // EOF
+				Some(pos) => pos,
+			};
+		};
+		if len == 0 {
+			return Ok(None);
+		}
+
+		self.count_token(len);
+		let data = self.buf.consume_mut(len);
+		let position = self.position;
+		self.position += len as u64;
+		// physical lines are what was actually consumed from the stream
+		if let Some(sink) = self.tee.as_mut() {
+			tee(sink, &mut self.teed, position, data)?;
+		}
+		// move physical lines to the front, one after another
+		let mut read = 0;
+		let mut written = 0;
+		loop {
+			let (mut end, next) = match memchr(b'\n', &data[read..]) {
+				Some(n) => (read + n, read + n + 1),
+				None => (len, len), // EOF
+			};
+			if next != end && end > read && data[end-1] == b'\r' {
+				end -= 1;
+			}
+			let is_continued = end > read && data[end-1] == cont;
+			if is_continued {
+				end -= 1;
+			}
+			data.copy_within(read..end, written);
+			written += end - read;
+			read = next;
+			if !is_continued || read == len { break }
+		}
+		Ok(Some(&data[..written]))
+	}
+}
+
+impl<R: Clone, B: CloneableBuffer> BufRefReader<R, B> {
+	/**
+	Duplicates this reader, so that both of them carry on from the same position independently of each other,
+	e.g. to try parsing the rest of the stream one way while keeping the option to go back and parse it another way.
+
+	The underlying reader is cloned, along with all the data that is buffered (see [`CloneableBuffer`](trait.CloneableBuffer.html)),
+	which only makes sense for readers that are cheap to clone, and whose clones read the same data (e.g. `&[u8]` or `io::Cursor`).
+	Clone does not [tee](#method.tee) anything, even if this reader does.
+	*/
+	pub fn try_clone(&self) -> Result<Self, B::Error> {
+		Ok(BufRefReader {
+			src: self.src.clone(),
+			buf: self.buf.try_clone()?,
+			config: self.config.clone(),
+			position: self.position,
+			eof: self.eof,
+			poisoned: self.poisoned,
+			finder: self.finder.clone(),
+			read_time: self.read_time,
+			scan_resume: self.scan_resume,
+			last_enlarge: self.last_enlarge,
+			tee: None,
+			teed: self.position,
+			tokens: self.tokens,
+			min_token_len: self.min_token_len,
+			max_token_len: self.max_token_len,
+		})
+	}
+}
+
+/// Same as [`BufRefReader::try_clone()`](struct.BufRefReader.html#method.try_clone), but panics if buffer cannot be allocated.
+impl<R: Clone, B: CloneableBuffer> Clone for BufRefReader<R, B> {
+	fn clone(&self) -> Self {
+		self.try_clone().unwrap_or_else(|_| panic!("failed to allocate buffer for the clone"))
+	}
+}
+
+impl<R: Read + Seek, B: Buffer> BufRefReader<R, B>
+where Error: From<B::Error>
+{
+	/**
+	Position of the next byte this reader returns within the underlying reader,
+	i.e. position of the underlying reader minus the amount of data that is buffered but not consumed yet.
+
+	Unlike [`position()`](#method.position), which counts bytes returned by this reader since it was created (or [reset](#method.reset)),
+	this also accounts for where the underlying reader was at the time.
+	As long as the underlying reader is only read by this `BufRefReader`,
+	the two only differ by a constant.
+
+	Fails with `ErrorKind::InvalidData` if more data is buffered than the underlying reader has gone past,
+	e.g. if the buffer is seeded with data that did not come from it (see [`from_parts()`](#method.from_parts)).
+	*/
+	pub fn stream_position(&mut self) -> io::Result<u64> {
+		self.src.stream_position()?
+			.checked_sub(self.buf.len() as u64)
+			.ok_or_else(|| io::Error::new(io::ErrorKind::InvalidData, "more data is buffered than the underlying reader has gone past"))
+	}
+
+	/**
+	Seeks the underlying reader, dropping whatever is buffered, and returns the new position within it.
+
+	`SeekFrom::Current` is relative to the next byte this reader would return (see [`stream_position()`](#method.stream_position)),
+	not to the position of the underlying reader.
+	Just like with [`reset()`](#method.reset), [`position()`](#method.position), [`stats()`](#method.stats) and [`read_time()`](#method.read_time) start over from 0,
+	the reader is no longer [poisoned](struct.BufRefReaderBuilder.html#method.poison_on_error),
+	and consumed data can no longer be [unread](#method.unread).
+
+	See also [`BufRefReaderBuilder::validate_after_seek()`](struct.BufRefReaderBuilder.html#method.validate_after_seek).
+	*/
+	pub fn seek(&mut self, pos: io::SeekFrom) -> Result<u64, Error> {
+		let pos = match pos {
+			io::SeekFrom::Current(offset) => {
+				let target = self.stream_position()?.checked_add_signed(offset)
+					.ok_or_else(|| io::Error::from(io::ErrorKind::InvalidInput))?;
+				io::SeekFrom::Start(target)
+			},
+			pos => pos,
+		};
+		// everything before this point was read successfully at some time
+		let valid_end = if self.config.validate_after_seek {
+			Some(self.src.stream_position()?)
+		} else {
+			None
+		};
+
+		let pos = self.src.seek(pos)?;
+		self.restart();
+
+		if let Some(end) = valid_end {
+			// whatever is read here is buffered as usual
+			if pos < end && self.fill()?.is_none() {
+				return Err(Error::SourceChanged);
+			}
+		}
+		Ok(pos)
+	}
+}
+
+impl<B: Buffer> BufRefReader<File, B>
+where Error: From<B::Error>
+{
+	/**
+	Opens a file, and creates buffered reader with initial capacity that matches its size:
+	files that are smaller than 256 KiB are read into the buffer as a whole (with buffer being no smaller than 4 KiB),
+	while larger files are read 256 KiB at a time (see also [`BufRefReaderBuilder::large()`](struct.BufRefReaderBuilder.html#method.large)).
+
+	Files that do not report their size (e.g. pipes) get the smallest buffer.
+	*/
+	pub fn open<P: AsRef<Path>>(path: P) -> Result<BufRefReader<File, B>, Error> {
+		let file = File::open(path)?;
+		let size = file.metadata()?.len();
+		let cap = std::cmp::min(size, 256*1024) as usize;
+		let cap = std::cmp::max(cap, 4*1024).next_power_of_two();
+		BufRefReaderBuilder::new(file)
+			.capacity(cap)
+			.build()
+	}
+}
+
+#[cfg(test)]
+static WORDS: &[u8] = include_bytes!("/usr/share/dict/words");
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+	use std::fmt::Debug;
+
+	fn read_until_empty_lines<B: Buffer>()
+	where
+		B::Error: Debug,
+		Error: From<B::Error>,
+	{
+		// two spaces, three spaces, two spaces
+		let mut r = BufRefReaderBuilder::new(&b"  lorem   ipsum  "[..])
+			.capacity(4)
+			.build::<B>()
+			.unwrap();
+		assert_eq!(r.read_until(b' ').unwrap(), Some(&b" "[..]));
+		assert_eq!(r.read_until(b' ').unwrap(), Some(&b" "[..]));
+		assert_eq!(r.read_until(b' ').unwrap(), Some(&b"lorem "[..]));
+		assert_eq!(r.read_until(b' ').unwrap(), Some(&b" "[..]));
+		assert_eq!(r.read_until(b' ').unwrap(), Some(&b" "[..]));
+		assert_eq!(r.read_until(b' ').unwrap(), Some(&b"ipsum "[..]));
+		assert_eq!(r.read_until(b' ').unwrap(), Some(&b" "[..]));
+		assert_eq!(r.read_until(b' ').unwrap(), None);
+	}
+
+	#[test] fn read_until_empty_lines_vec()  { read_until_empty_lines::<VecBuffer>() }
+	#[test] fn read_until_empty_lines_mmap() { read_until_empty_lines::<MmapBuffer>() }
+
+	fn read_until_fill_boundary<B: Buffer>()
+	where
+		B::Error: Debug,
+		Error: From<B::Error>,
+	{
+		// every byte comes with its own fill, so delimiter is always the first byte of a fill that follows the rest of the token
+		let mut r = BufRefReaderBuilder::new(OneByteReader(b"a\nbc\n\nd"))
+			.capacity(1)
+			.build::<B>()
+			.unwrap();
+		assert_eq!(r.read_until(b'\n').unwrap(), Some(&b"a\n"[..]));
+		assert_eq!(r.read_until(b'\n').unwrap(), Some(&b"bc\n"[..]));
+		assert_eq!(r.read_until(b'\n').unwrap(), Some(&b"\n"[..]));
+		assert_eq!(r.read_until(b'\n').unwrap(), Some(&b"d"[..]));
+		assert_eq!(r.read_until(b'\n').unwrap(), None);
+
+		// multi-byte delimiter that is split between fills, including a partial match that turns out to be a false one
+		let mut r = BufRefReaderBuilder::new(OneByteReader(b"a<>b<<>c<"))
+			.capacity(1)
+			.build::<B>()
+			.unwrap();
+		assert_eq!(r.read_until_bytes(b"<>").unwrap(), Some(&b"a<>"[..]));
+		assert_eq!(r.read_until_bytes(b"<>").unwrap(), Some(&b"b<<>"[..]));
+		assert_eq!(r.read_until_bytes(b"<>").unwrap(), Some(&b"c<"[..]));
+		assert_eq!(r.read_until_bytes(b"<>").unwrap(), None);
+	}
+
+	#[test] fn read_until_fill_boundary_vec()  { read_until_fill_boundary::<VecBuffer>() }
+	#[test] fn read_until_fill_boundary_mmap() { read_until_fill_boundary::<MmapBuffer>() }
+
+	fn read_until_words<B: Buffer>()
+	where
+		B::Error: Debug,
+		Error: From<B::Error>,
+	{
+		let mut r = BufRefReaderBuilder::new(WORDS)
+			.capacity(4) // rather pointless for MmapBuffer that rounds up to 4096 or whatever (see read_until_words_long)
+			.build::<B>()
+			.unwrap();
+		let mut words = WORDS.split(|&c| c == b'\n');
+		while let Ok(Some(slice_buf)) = r.read_until(b'\n') {
+			let mut ref_word = words.next().unwrap()
+				.to_vec();
+			ref_word.push(b'\n');
+			assert_eq!(slice_buf, &ref_word[..]);
+		}
+
+		// reader: returned immediately after hitting EOF past last b'\n'
+		// words: this is .split(), hence empty string past last b'\n'
+		assert_eq!(words.next(), Some(&b""[..]));
+
+		assert_eq!(words.next(), None);
+	}
+
+	#[test] fn read_until_words_vec()  { read_until_words::<VecBuffer>() }
+	#[test] fn read_until_words_mmap() { read_until_words::<MmapBuffer>() }
+	#[cfg(feature = "mlock")]
+	#[test] fn read_until_words_mlock() { read_until_words::<MlockBuffer>() }
+
+	// like read_until_words, but splits by rarest character, which is b'Q'
+	// this also ensures that MmapBuffer (that rounds capacity up to the page size right from the beginning) is going to reallocate itself at least once
+	fn read_until_words_long<B: Buffer>()
+	where
+		B::Error: Debug,
+		Error: From<B::Error>,
+	{
+		let mut r = BufRefReaderBuilder::new(WORDS)
+			.capacity(4096)
+			.build::<B>()
+			.unwrap();
+		let mut words = WORDS.split(|&c| c == b'Q').peekable();
+		while let Ok(Some(slice_buf)) = r.read_until(b'Q') {
+			let mut ref_word = words.next().unwrap()
+				.to_vec();
+			if words.peek().is_some() {
+				ref_word.push(b'Q');
+			}
+			assert_eq!(slice_buf, &ref_word[..]);
+		}
+
+		assert_eq!(words.next(), None);
+	}
+
+	#[test] fn read_until_words_long_vec()  { read_until_words_long::<VecBuffer>() }
+	#[test] fn read_until_words_long_mmap() { read_until_words_long::<MmapBuffer>() }
+	#[cfg(feature = "mlock")]
+	#[test] fn read_until_words_long_mlock() { read_until_words_long::<MlockBuffer>() }
+
+	#[test]
+	fn time_reads() {
+		struct SlowReader<'a> {
+			src: &'a [u8],
+			reads: u32,
+		}
+		impl Read for SlowReader<'_> {
+			fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+				self.reads += 1;
+				std::thread::sleep(Duration::from_millis(1));
+				self.src.read(buf)
+			}
+		}
+
+		let mut r = BufRefReaderBuilder::new(SlowReader { src: b"lorem\nipsum\ndolor", reads: 0 })
+			.capacity(4)
+			.time_reads(true)
+			.build::<VecBuffer>()
+			.unwrap();
+		while r.read_until(b'\n').unwrap().is_some() {}
+		assert!(r.read_time() >= Duration::from_millis(1) * r.src.reads);
+
+		r.reset(SlowReader { src: b"lorem", reads: 0 });
+		assert_eq!(r.read_time(), Duration::ZERO);
+
+		// not measured by default
+		let mut r = BufRefReaderBuilder::new(SlowReader { src: b"lorem\nipsum\ndolor", reads: 0 })
+			.capacity(4)
+			.build::<VecBuffer>()
+			.unwrap();
+		while r.read_until(b'\n').unwrap().is_some() {}
+		assert_eq!(r.read_time(), Duration::ZERO);
+	}
+
+	#[test]
+	fn stats() {
+		let mut r = BufRefReaderBuilder::new(&b"\xef\xbb\xbfa\nbb\nccc\n\ndddd"[..])
+			.capacity(4)
+			.build::<VecBuffer>()
+			.unwrap();
+		assert_eq!(r.stats(), Stats::default());
+		assert_eq!(r.min_token_len(), None);
+		assert_eq!(r.skip_bom().unwrap(), Some(Bom::Utf8));
+		while r.read_until(b'\n').unwrap().is_some() {}
+		assert_eq!(r.stats(), Stats {
+			bytes: 17,
+			tokens: 5,
+			min_token_len: Some(1),
+			max_token_len: Some(4),
+		});
+		assert_eq!(r.min_token_len(), Some(1));
+		assert_eq!(r.max_token_len(), Some(4));
+
+		r.reset(&b"lorem ipsum"[..]);
+		assert_eq!(r.stats(), Stats::default());
+		assert_eq!(r.read(8).unwrap(), Some(&b"lorem ip"[..]));
+		assert_eq!(r.read(8).unwrap(), Some(&b"sum"[..]));
+		assert_eq!(r.stats(), Stats {
+			bytes: 11,
+			tokens: 2,
+			min_token_len: Some(3),
+			max_token_len: Some(8),
+		});
+	}
+
+	#[test]
+	fn last_enlarge() {
+		let mut r = BufRefReaderBuilder::new(&b"lorem\nipsum dolor sit amet\n"[..])
+			.capacity(8)
+			.build::<VecBuffer>()
+			.unwrap();
+		assert_eq!(r.read_until(b'\n').unwrap(), Some(&b"lorem\n"[..]));
+		assert_eq!(r.last_enlarge(), None);
+		// "ip" is moved to the beginning of the buffer, and then there's no room left for the rest of the token
+		assert_eq!(r.read(4).unwrap(), Some(&b"ipsu"[..]));
+		assert_eq!(r.last_enlarge(), Some(EnlargeResult::Compacted));
+		assert_eq!(r.read_until(b'\n').unwrap(), Some(&b"m dolor sit amet\n"[..]));
+		assert_eq!(r.last_enlarge(), Some(EnlargeResult::Reallocated));
+
+		r.reset(&b""[..]);
+		assert_eq!(r.last_enlarge(), None);
+	}
+
+	fn read_until_budgeted<B: Buffer>()
+	where
+		B::Error: Debug,
+		Error: From<B::Error>,
+	{
+		let mut data = vec![b'x'; 100];
+		data.extend_from_slice(b"\nlorem\nipsum");
+		let mut r = BufRefReaderBuilder::new(&data[..])
+			.capacity(8)
+			.build::<B>()
+			.unwrap();
+
+		let mut pending = 0;
+		let token = loop {
+			match r.read_until_budgeted(b'\n', 16).unwrap() {
+				ScanState::Pending => pending += 1,
+				ScanState::Token(token) => break token.to_vec(),
+				ScanState::Eof => panic!("unexpected EOF"),
+			}
+		};
+		// every call only scans as much as it's allowed to
+		assert_eq!(pending, 100 / 16);
+		assert_eq!(token, &data[..101]);
+
+		assert_eq!(r.read_until_budgeted(b'\n', 16).unwrap(), ScanState::Token(b"lorem\n"));
+		assert_eq!(r.read_until_budgeted(b'\n', 3).unwrap(), ScanState::Pending);
+		// scan starts over once something else is consumed
+		assert_eq!(r.read(1).unwrap(), Some(&b"i"[..]));
+		assert_eq!(r.read_until_budgeted(b'\n', 3).unwrap(), ScanState::Pending);
+		assert_eq!(r.read_until_budgeted(b'\n', 3).unwrap(), ScanState::Token(b"psum"));
+		assert_eq!(r.read_until_budgeted(b'\n', 3).unwrap(), ScanState::Eof);
+	}
+
+	#[test] fn read_until_budgeted_vec()  { read_until_budgeted::<VecBuffer>() }
+	#[test] fn read_until_budgeted_mmap() { read_until_budgeted::<MmapBuffer>() }
+
+	fn open<B: Buffer>()
+	where
+		B::Error: Debug,
+		Error: From<B::Error>,
+	{
+		let path = std::env::temp_dir().join(format!("buf-ref-reader-open-{}-{}", std::process::id(), std::any::type_name::<B>()));
+		let reads = |data: &[u8]| {
+			std::fs::write(&path, data).unwrap();
+			let mut r = BufRefReader::<_, B>::open(&path).unwrap();
+			let cap = r.capacity();
+			let mut rebuilt = vec![];
+			while let Some(chunk) = r.read_some(cap).unwrap() {
+				rebuilt.extend_from_slice(chunk);
+			}
+			assert_eq!(&rebuilt[..], data);
+			cap
+		};
+		assert_eq!(reads(b""), B::effective_capacity(4096));
+		assert_eq!(reads(&WORDS[..5000]), B::effective_capacity(8192));
+		assert_eq!(reads(WORDS), B::effective_capacity(256*1024));
+		std::fs::remove_file(&path).unwrap();
+
+		assert!(BufRefReader::<_, B>::open(&path).is_err());
+	}
+
+	#[test] fn open_vec()  { open::<VecBuffer>() }
+	#[test] fn open_mmap() { open::<MmapBuffer>() }
+
+	fn increment<B: Buffer>()
+	where
+		B::Error: Debug,
+		Error: From<B::Error>,
+	{
+		let unit = B::effective_capacity(4096);
+		let mut data = vec![b'x'; unit * 5 / 2];
+		data.push(b'\n');
+		let mut r = BufRefReaderBuilder::new(&data[..])
+			.capacity(unit)
+			.increment(unit)
+			.build::<B>()
+			.unwrap();
+		assert_eq!(r.read_until(b'\n').unwrap(), Some(&data[..]));
+		// rather than doubled twice
+		assert_eq!(r.capacity(), unit * 3);
+	}
+
+	#[test] fn increment_vec()  { increment::<VecBuffer>() }
+	#[test] fn increment_mmap() { increment::<MmapBuffer>() }
+
+	fn min_read<B: Buffer>()
+	where
+		B::Error: Debug,
+		Error: From<B::Error>,
+	{
+		// records how much room every read had
+		struct SizeReader<'a> {
+			src: &'a [u8],
+			sizes: Vec<usize>,
+		}
+		impl Read for SizeReader<'_> {
+			fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+				self.sizes.push(buf.len());
+				self.src.read(buf)
+			}
+		}
+
+		let unit = B::effective_capacity(4096);
+		let mut r = BufRefReaderBuilder::new(SizeReader { src: WORDS, sizes: vec![] })
+			.capacity(unit)
+			.min_read(unit / 2)
+			.build::<B>()
+			.unwrap();
+		let mut lines = WORDS.split_inclusive(|&c| c == b'\n');
+		while let Some(line) = r.read_until(b'\n').unwrap() {
+			assert_eq!(Some(line), lines.next());
+		}
+		assert_eq!(lines.next(), None);
+		// lines are short, so making room for reads never takes more than compaction
+		assert_eq!(r.capacity(), unit);
+		let (src, _) = r.take_buffer();
+		assert!(src.sizes.iter().all(|&size| size >= unit / 2), "{:?}", src.sizes);
+	}
+
+	#[test] fn min_read_vec()  { min_read::<VecBuffer>() }
+	#[test] fn min_read_mmap() { min_read::<MmapBuffer>() }
+
+	#[test]
+	fn min_read_grow() {
+		let mut r = BufRefReaderBuilder::new(&b"lorem ipsum dolor"[..])
+			.capacity(8)
+			.min_read(7)
+			.build::<VecBuffer>()
+			.unwrap();
+		assert_eq!(r.read_until(b' ').unwrap(), Some(&b"lorem "[..]));
+		// "ip" is left, and there's no room for 7 more bytes even after compaction
+		assert_eq!(r.read_until(b' ').unwrap(), Some(&b"ipsum "[..]));
+		assert_eq!(r.last_enlarge(), Some(EnlargeResult::Reallocated));
+		assert_eq!(r.read_until(b' ').unwrap(), Some(&b"dolor"[..]));
+
+		// fixed buffer reads into whatever room is left instead
+		let mut r = BufRefReaderBuilder::new(&b"lorem ipsum dolor"[..])
+			.capacity(8)
+			.min_read(7)
+			.fixed(true)
+			.build::<VecBuffer>()
+			.unwrap();
+		assert_eq!(r.read_until(b' ').unwrap(), Some(&b"lorem "[..]));
+		assert_eq!(r.read_until(b' ').unwrap(), Some(&b"ipsum "[..]));
+		assert_eq!(r.read_until(b' ').unwrap(), Some(&b"dolor"[..]));
+		assert_eq!(r.capacity(), 8);
+	}
+
+	fn advise<B: Buffer>()
+	where
+		B::Error: Debug,
+		Error: From<B::Error>,
+	{
+		for &advice in &[Advice::Normal, Advice::Sequential, Advice::Random, Advice::WillNeed] {
+			let mut r = BufRefReaderBuilder::new(WORDS)
+				.capacity(16)
+				.advise(advice)
+				.build::<B>()
+				.unwrap();
+			let mut lines = WORDS.split_inclusive(|&c| c == b'\n');
+			while let Some(line) = r.read_until(b'\n').unwrap() {
+				assert_eq!(Some(line), lines.next());
+			}
+			assert_eq!(lines.next(), None);
+		}
+	}
+
+	#[test] fn advise_vec()  { advise::<VecBuffer>() }
+	#[test] fn advise_mmap() { advise::<MmapBuffer>() }
+
+	fn zero_on_drop<B: Buffer>()
+	where
+		B::Error: Debug,
+		Error: From<B::Error>,
+	{
+		// wiping itself is tested by buffers, this only makes sure nothing is lost along the way
+		let mut r = BufRefReaderBuilder::new(WORDS)
+			.capacity(4)
+			.zero_on_drop(true)
+			.build::<B>()
+			.unwrap();
+		let mut lines = WORDS.split_inclusive(|&c| c == b'\n');
+		while let Some(line) = r.read_until(b'\n').unwrap() {
+			assert_eq!(Some(line), lines.next());
+		}
+		assert_eq!(lines.next(), None);
+	}
+
+	#[test] fn zero_on_drop_vec()  { zero_on_drop::<VecBuffer>() }
+	#[test] fn zero_on_drop_mmap() { zero_on_drop::<MmapBuffer>() }
+
+	fn read_until_any_seq<B: Buffer>()
+	where
+		B::Error: Debug,
+		Error: From<B::Error>,
+	{
+		let read_all = |data: &'static [u8], needles: &[&[u8]]| {
+			let mut r = BufRefReaderBuilder::new(OneByteReader(data))
+				.capacity(1)
+				.build::<B>()
+				.unwrap();
+			let mut tokens = vec![];
+			while let Some((token, found)) = r.read_until_any_seq(needles).unwrap() {
+				tokens.push((token.to_vec(), found));
+			}
+			tokens
+		};
+		let tok = |token: &[u8], found| (token.to_vec(), found);
+
+		// longest of the needles that begin at the same position
+		assert_eq!(read_all(b"a-->b->c--d", &[b"->", b"-->"]), vec![
+			tok(b"a-->", Some(1)),
+			tok(b"b->", Some(0)),
+			tok(b"c--d", None),
+		]);
+		assert_eq!(read_all(b"a-->b--c--", &[b"--", b"-->"]), vec![
+			tok(b"a-->", Some(1)),
+			tok(b"b--", Some(0)),
+			tok(b"c--", Some(0)),
+		]);
+		// earliest of the needles, even if it ends later
+		assert_eq!(read_all(b"xabcyb", &[b"b", b"abc"]), vec![
+			tok(b"xabc", Some(1)),
+			tok(b"yb", Some(0)),
+		]);
+		assert_eq!(read_all(b"lorem", &[]), vec![tok(b"lorem", None)]);
+		assert_eq!(read_all(b"", &[b"->"]), vec![]);
+	}
+
+	#[test] fn read_until_any_seq_vec()  { read_until_any_seq::<VecBuffer>() }
+	#[test] fn read_until_any_seq_mmap() { read_until_any_seq::<MmapBuffer>() }
+
+	fn read_until_and_rest<B: Buffer>()
+	where
+		B::Error: Debug,
+		Error: From<B::Error>,
+	{
+		let mut r = BufRefReaderBuilder::new(WORDS)
+			.capacity(16)
+			.build::<B>()
+			.unwrap();
+		let mut words = WORDS.split(|&c| c == b'\n');
+		loop {
+			let (token, rest) = match r.read_until_and_rest(b'\n').unwrap() {
+				None => break,
+				Some((token, rest)) => (token.to_vec(), rest.to_vec()),
+			};
+			let mut word = words.next().unwrap().to_vec();
+			word.push(b'\n');
+			assert_eq!(token, word);
+			assert_eq!(rest, r.buffered());
+		}
+		assert_eq!(words.next(), Some(&b""[..]));
+		assert_eq!(words.next(), None);
+		assert_eq!(r.position(), WORDS.len() as u64);
+	}
+
+	#[test] fn read_until_and_rest_vec()  { read_until_and_rest::<VecBuffer>() }
+	#[test] fn read_until_and_rest_mmap() { read_until_and_rest::<MmapBuffer>() }
+
+	#[test]
+	fn read_until_flat() {
+		let src = FailingReader { src: &b"lorem\nipsum"[..], fail_after: Some(8) };
+		let mut r = BufRefReaderBuilder::new(src)
+			.capacity(4)
+			.build::<VecBuffer>()
+			.unwrap();
+		match r.read_until_flat(b'\n') {
+			ReadResult::Data(data) => assert_eq!(data, b"lorem\n"),
+			x => panic!("expected Data, got {:?}", x),
+		}
+		match r.read_until_flat(b'\n') {
+			ReadResult::Err(Error::IO(_)) => (),
+			x => panic!("expected Err, got {:?}", x),
+		}
+		match r.read_until_flat(b'\n') {
+			ReadResult::Data(data) => assert_eq!(data, b"ipsum"),
+			x => panic!("expected Data, got {:?}", x),
+		}
+		match r.read_until_flat(b'\n') {
+			ReadResult::Eof => (),
+			x => panic!("expected Eof, got {:?}", x),
+		}
+	}
+
+	#[test]
+	fn error_variants() {
+		let err = |r: Result<BufRefReader<&[u8], VecBuffer>, Error>| r.unwrap_err();
+		match err(BufRefReaderBuilder::new(&b""[..]).capacity(isize::MAX as usize + 1).build()) {
+			Error::CapacityOverflow => (),
+			x => panic!("expected CapacityOverflow, got {:?}", x),
+		}
+		match err(BufRefReaderBuilder::new(&b""[..]).capacity(isize::MAX as usize).build()) {
+			Error::AllocFailed => (),
+			x => panic!("expected AllocFailed, got {:?}", x),
+		}
+
+		let mut r = BufRefReaderBuilder::new(&b"lorem ipsum"[..])
+			.capacity(4)
+			.fixed(true)
+			.build::<VecBuffer>()
+			.unwrap();
+		match r.read_until(b'\n') {
+			Err(Error::BufferFull) => (),
+			x => panic!("expected BufferFull, got {:?}", x),
+		}
+
+		let mut r = BufRefReaderBuilder::new(FailingReader { src: b"lorem", fail_after: Some(0) })
+			.build::<VecBuffer>()
+			.unwrap();
+		match r.read_until(b'\n') {
+			Err(Error::IO(_)) => (),
+			x => panic!("expected IO, got {:?}", x),
+		}
+	}
+
+	#[test]
+	fn invalid_config() {
+		let build = |config: Config| BufRefReaderBuilder::from_config(&b""[..], config).build::<VecBuffer>();
+		let check = |config: Config, reason: &str| match build(config) {
+			Err(Error::InvalidConfig(r)) => assert_eq!(r, reason),
+			Err(x) => panic!("expected InvalidConfig, got {:?}", x),
+			Ok(_) => panic!("expected InvalidConfig, got a reader"),
+		};
+		check(Config { capacity: 0, ..Config::default() }, "capacity must be larger than 0");
+		check(Config { increment: Some(0), ..Config::default() }, "increment must be larger than 0");
+		check(Config { min_read: 0, ..Config::default() }, "min_read must be larger than 0");
+		check(Config { record_delimiter: Some(vec![]), ..Config::default() }, "record delimiter must not be empty");
+		check(Config { fixed: true, increment: Some(16), ..Config::default() }, "increment is set for a fixed buffer, which never grows");
+		match BufRefReaderBuilder::new(&b""[..]).zero_on_drop(true).build::<buffer::PlainBuffer>() {
+			Err(Error::InvalidConfig(r)) => assert_eq!(r, "zero_on_drop is not supported by the buffer"),
+			x => panic!("expected InvalidConfig, got {:?}", x.map(|_| ())),
+		}
+		// setters are no different
+		match BufRefReaderBuilder::new(&b""[..]).min_read(0).build::<VecBuffer>() {
+			Err(Error::InvalidConfig(r)) => assert_eq!(r, "min_read must be larger than 0"),
+			x => panic!("expected InvalidConfig, got {:?}", x.map(|_| ())),
+		}
+
+		assert!(build(Config::default()).is_ok());
+		assert!(build(Config { increment: Some(16), ..Config::default() }).is_ok());
+		assert!(build(Config { fixed: true, readahead: 4, ..Config::default() }).is_ok());
+		assert!(build(Config { readahead: 0, ..Config::default() }).is_ok());
+		assert!(build(Config { record_delimiter: Some(b"\r\n".to_vec()), ..Config::default() }).is_ok());
+	}
+
+	fn require_final_delim<B: Buffer>()
+	where
+		B::Error: Debug,
+		Error: From<B::Error>,
+	{
+		let reader = |require| BufRefReaderBuilder::new(OneByteReader(b"lorem\nipsum\ndolor"))
+			.capacity(4)
+			.require_final_delim(require)
+			.build::<B>()
+			.unwrap();
+
+		let mut r = reader(false);
+		assert_eq!(r.read_until(b'\n').unwrap(), Some(&b"lorem\n"[..]));
+		assert_eq!(r.read_until(b'\n').unwrap(), Some(&b"ipsum\n"[..]));
+		assert_eq!(r.read_until(b'\n').unwrap(), Some(&b"dolor"[..]));
+		assert_eq!(r.read_until(b'\n').unwrap(), None);
+
+		let mut r = reader(true);
+		assert_eq!(r.read_until(b'\n').unwrap(), Some(&b"lorem\n"[..]));
+		assert_eq!(r.read_until(b'\n').unwrap(), Some(&b"ipsum\n"[..]));
+		match r.read_until(b'\n') {
+			Err(Error::MissingFinalDelimiter) => (),
+			x => panic!("expected MissingFinalDelimiter, got {:?}", x),
+		}
+		// partial data is still there
+		assert_eq!(r.buffered(), b"dolor");
+		assert_eq!(r.position(), 12);
+		assert_eq!(r.read(5).unwrap(), Some(&b"dolor"[..]));
+		assert_eq!(r.read_until(b'\n').unwrap(), None);
+
+		// stream that ends with delimiter is fine
+		let mut r = BufRefReaderBuilder::new(&b"lorem\n"[..])
+			.require_final_delim(true)
+			.build::<B>()
+			.unwrap();
+		assert_eq!(r.read_until(b'\n').unwrap(), Some(&b"lorem\n"[..]));
+		assert_eq!(r.read_until(b'\n').unwrap(), None);
+	}
+
+	#[test] fn require_final_delim_vec()  { require_final_delim::<VecBuffer>() }
+	#[test] fn require_final_delim_mmap() { require_final_delim::<MmapBuffer>() }
+
+	// shared, so that test can look at what was written while reader still owns the sink
+	#[derive(Clone, Default)]
+	struct SharedSink(std::sync::Arc<std::sync::Mutex<Vec<u8>>>);
+	impl Write for SharedSink {
+		fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+			self.0.lock().unwrap().write(buf)
+		}
+		fn flush(&mut self) -> io::Result<()> {
+			Ok(())
+		}
+	}
+
+	fn tee<B: Buffer>()
+	where
+		B::Error: Debug,
+		Error: From<B::Error>,
+	{
+		let sink = SharedSink::default();
+		let mut r = BufRefReaderBuilder::new(WORDS)
+			.capacity(16)
+			.build::<B>()
+			.unwrap()
+			.tee(sink.clone());
+		let mut tokens = vec![];
+		for i in 0.. {
+			// peeked data is not written until it is consumed
+			if r.peek_until(b'\n').unwrap().is_none() {
+				break;
+			}
+			let token = r.read_until(b'\n').unwrap().unwrap();
+			if i % 3 == 0 {
+				// unread data is not written twice
+				let len = token.len();
+				r.unread(len).unwrap();
+				continue;
+			}
+			tokens.extend_from_slice(token);
+			assert_eq!(sink.0.lock().unwrap().len(), tokens.len());
+		}
+		assert_eq!(tokens, WORDS);
+		assert_eq!(*sink.0.lock().unwrap(), WORDS);
+	}
+
+	#[test] fn tee_vec()  { tee::<VecBuffer>() }
+	#[test] fn tee_mmap() { tee::<MmapBuffer>() }
+
+	#[test]
+	fn tee_failed() {
+		struct FailingSink;
+		impl Write for FailingSink {
+			fn write(&mut self, _buf: &[u8]) -> io::Result<usize> {
+				Err(io::Error::other("sink is full"))
+			}
+			fn flush(&mut self) -> io::Result<()> {
+				Ok(())
+			}
+		}
+
+		let mut r = BufRefReaderBuilder::new(&b"lorem\nipsum\n"[..])
+			.build::<VecBuffer>()
+			.unwrap()
+			.tee(FailingSink);
+		match r.read_until(b'\n') {
+			Err(Error::Tee(_)) => (),
+			x => panic!("expected Tee, got {:?}", x),
+		}
+		// data is consumed nevertheless
+		assert_eq!(r.position(), 6);
+		assert!(matches!(r.read_until(b'\n'), Err(Error::Tee(_))));
+	}
+
+	fn read_until_in_range<B: Buffer>()
+	where
+		B::Error: Debug,
+		Error: From<B::Error>,
+	{
+		let mut r = BufRefReaderBuilder::new(OneByteReader(b"lorem\tipsum\x7f dolor\r\n\x00sit amet"))
+			.capacity(4)
+			.build::<B>()
+			.unwrap();
+		let control = 0..=31;
+		assert_eq!(r.read_until_in_range(control.clone()).unwrap(), Some(&b"lorem\t"[..]));
+		// range is inclusive
+		assert_eq!(r.read_until_in_range(0x7f..=0x7f).unwrap(), Some(&b"ipsum\x7f"[..]));
+		assert_eq!(r.read_until_in_range(control.clone()).unwrap(), Some(&b" dolor\r"[..]));
+		assert_eq!(r.read_until_in_range(control.clone()).unwrap(), Some(&b"\n"[..]));
+		assert_eq!(r.read_until_in_range(control.clone()).unwrap(), Some(&b"\x00"[..]));
+		// no control characters till EOF
+		assert_eq!(r.read_until_in_range(control.clone()).unwrap(), Some(&b"sit amet"[..]));
+		assert_eq!(r.read_until_in_range(control).unwrap(), None);
+	}
+
+	#[test] fn read_until_in_range_vec()  { read_until_in_range::<VecBuffer>() }
+	#[test] fn read_until_in_range_mmap() { read_until_in_range::<MmapBuffer>() }
+
+	fn read_until_set<B: Buffer>()
+	where
+		B::Error: Debug,
+		Error: From<B::Error>,
+	{
+		// everything but lowercase letters
+		let set = ByteSet::from_fn(|c| !c.is_ascii_lowercase());
+		let mut r = BufRefReaderBuilder::new(WORDS)
+			.capacity(16)
+			.build::<B>()
+			.unwrap();
+		let mut pos = 0;
+		while let Some(token) = r.read_until_set(&set).unwrap() {
+			let len = WORDS[pos..].iter().position(|c| !c.is_ascii_lowercase()).map_or(WORDS.len() - pos, |n| n + 1);
+			assert_eq!(token, &WORDS[pos..pos+len]);
+			pos += len;
+		}
+		assert_eq!(pos, WORDS.len());
+
+		// nothing to look for
+		let mut r = BufRefReaderBuilder::new(OneByteReader(b"lorem\nipsum"))
+			.capacity(4)
+			.build::<B>()
+			.unwrap();
+		assert_eq!(r.read_until_set(&ByteSet::default()).unwrap(), Some(&b"lorem\nipsum"[..]));
+		assert_eq!(r.read_until_set(&ByteSet::default()).unwrap(), None);
+	}
+
+	#[test] fn read_until_set_vec()  { read_until_set::<VecBuffer>() }
+	#[test] fn read_until_set_mmap() { read_until_set::<MmapBuffer>() }
+
+	fn read_word<B: Buffer>()
+	where
+		B::Error: Debug,
+		Error: From<B::Error>,
+	{
+		let words = |data: &'static [u8]| {
+			let mut r = BufRefReaderBuilder::new(OneByteReader(data))
+				.capacity(1)
+				.build::<B>()
+				.unwrap();
+			let mut words = vec![];
+			while let Some(word) = r.read_word().unwrap() {
+				words.push(String::from_utf8(word.to_vec()).unwrap());
+			}
+			words
+		};
+		assert_eq!(words(b"  lorem\tipsum \r\n\x0b\x0cdolor\n\nsit amet \n"), vec!["lorem", "ipsum", "dolor", "sit", "amet"]);
+		assert_eq!(words(b"lorem"), vec!["lorem"]);
+		assert_eq!(words(b" \t\n "), Vec::<String>::new());
+		assert_eq!(words(b""), Vec::<String>::new());
+
+		// only a single whitespace byte is consumed with the word
+		let mut r = BufRefReaderBuilder::new(&b"lorem  ipsum"[..])
+			.build::<B>()
+			.unwrap();
+		assert_eq!(r.read_word().unwrap(), Some(&b"lorem"[..]));
+		assert_eq!(r.buffered(), b" ipsum");
+	}
+
+	#[test] fn read_word_vec()  { read_word::<VecBuffer>() }
+	#[test] fn read_word_mmap() { read_word::<MmapBuffer>() }
+
+	#[test]
+	fn read_buffered() {
+		let mut r = BufRefReaderBuilder::new(CountingReader { src: WORDS, reads: 0 })
+			.capacity(4096)
+			.build::<VecBuffer>()
+			.unwrap();
+		let buffered = r.prefetch(4096).unwrap();
+		let reads = r.src.reads;
+		let layout = r.buffer_layout();
+		let mut pos = 0;
+		while pos + 7 <= buffered {
+			assert_eq!(r.read(7).unwrap(), Some(&WORDS[pos..pos+7]));
+			pos += 7;
+		}
+		assert_eq!(r.src.reads, reads);
+		// nothing was moved or reallocated either
+		assert_eq!(r.last_enlarge(), None);
+		assert_eq!(r.buffer_layout().capacity, layout.capacity);
+		assert_eq!(r.buffer_layout().appendable_len, layout.appendable_len);
+	}
+
+	#[test]
+	fn read_zero() {
+		let mut r = BufRefReaderBuilder::new(CountingReader { src: b"lorem", reads: 0 })
+			.build::<VecBuffer>()
+			.unwrap();
+		// nothing is buffered yet
+		assert_eq!(r.read(0).unwrap(), Some(&b""[..]));
+		assert_eq!(r.src.reads, 0);
+
+		assert_eq!(r.read(2).unwrap(), Some(&b"lo"[..]));
+		let reads = r.src.reads;
+		// something is buffered
+		assert_eq!(r.read(0).unwrap(), Some(&b""[..]));
+		assert_eq!(r.read(3).unwrap(), Some(&b"rem"[..]));
+		// past EOF
+		assert_eq!(r.read(0).unwrap(), Some(&b""[..]));
+		assert_eq!(r.src.reads, reads);
+		assert_eq!(r.read(1).unwrap(), None);
+	}
+
+	#[test]
+	fn read_into_array() {
+		let mut r = BufRefReaderBuilder::new(&b"loremipsumdo"[..])
+			.capacity(3)
+			.build::<VecBuffer>()
+			.unwrap();
+		assert_eq!(r.read_into_array::<4>().unwrap(), Some(*b"lore"));
+		assert_eq!(r.read_into_array::<6>().unwrap(), Some(*b"mipsum"));
+		match r.read_into_array::<4>() {
+			Err(Error::IO(err)) => assert_eq!(err.kind(), io::ErrorKind::UnexpectedEof),
+			x => panic!("expected UnexpectedEof, got {:?}", x),
+		}
+		assert_eq!(r.read_into_array::<4>().unwrap(), None);
+	}
+
+	#[test]
+	fn skip_bom() {
+		let check = |data: &[u8], bom, rest: &[u8]| {
+			let mut r = BufRefReaderBuilder::new(OneByteReader(data))
+				.capacity(1)
+				.build::<VecBuffer>()
+				.unwrap();
+			assert_eq!(r.skip_bom().unwrap(), bom);
+			// there's only one
+			assert_eq!(r.skip_bom().unwrap(), None);
+			assert_eq!(r.read(100).unwrap().unwrap_or(b""), rest);
+		};
+		check(b"\xef\xbb\xbflorem", Some(Bom::Utf8), b"lorem");
+		check(b"\xff\xfel\x00", Some(Bom::Utf16Le), b"l\x00");
+		check(b"\xfe\xff\x00l", Some(Bom::Utf16Be), b"\x00l");
+		check(b"\xfe\xff", Some(Bom::Utf16Be), b"");
+		// nothing is consumed without BOM
+		check(b"lorem", None, b"lorem");
+		check(b"\xef\xbblorem", None, b"\xef\xbblorem");
+		check(b"\xef\xbb", None, b"\xef\xbb");
+		check(b"", None, b"");
+	}
+
+	fn read_wrapped<B: Buffer>()
+	where
+		B::Error: Debug,
+		Error: From<B::Error>,
+	{
+		let mut r = BufRefReaderBuilder::new(WORDS)
+			.capacity(4096)
+			.build::<B>()
+			.unwrap();
+		let cap = r.capacity();
+		// odd sizes make sure that chunks keep crossing the end of the ring
+		let mut pos = 0;
+		for n in [cap - 1, cap / 3, cap - 7, cap / 2 + 1].iter().cycle().take(100) {
+			let chunk = r.read(*n).unwrap().unwrap();
+			assert_eq!(chunk, &WORDS[pos..pos+n]);
+			pos += n;
+		}
+		// never had to grow to return contiguous data
+		assert_eq!(r.capacity(), cap);
+	}
+
+	#[test] fn read_wrapped_vec()  { read_wrapped::<VecBuffer>() }
+	#[test] fn read_wrapped_mmap() { read_wrapped::<MmapBuffer>() }
+
+	fn read_until_stale<B: Buffer>()
+	where
+		B::Error: Debug,
+		Error: From<B::Error>,
+	{
+		let cap = BufRefReaderBuilder::new(&b""[..]).capacity(4096).effective_capacity::<B>();
+		// consumed delimiters are left all over the buffer (and its mirror, if any)...
+		let delims = vec![b'\n'; cap - 100];
+		// ...and then the token that crosses the end of the ring is appended over them
+		let token = [b'y'; 200];
+		let mut r = BufRefReaderBuilder::new(Read::chain(&delims[..], &token[..]))
+			.capacity(4096)
+			.build::<B>()
+			.unwrap();
+		for _ in 0..delims.len() {
+			assert_eq!(r.read_until(b'\n').unwrap(), Some(&b"\n"[..]));
+		}
+		// no phantom delimiters right past the end of appended data
+		assert_eq!(r.peek_until(b'\n').unwrap(), Some(&token[..]));
+		let layout = r.buffer_layout();
+		if matches!(r.buffer_kind(), BufferKind::MmapRing) {
+			// make sure the token does cross the end of the ring
+			assert!(layout.start + layout.len > layout.capacity);
+		}
+		assert_eq!(r.read_until(b'\n').unwrap(), Some(&token[..]));
+		assert_eq!(r.read_until(b'\n').unwrap(), None);
+	}
+
+	#[test] fn read_until_stale_vec()  { read_until_stale::<VecBuffer>() }
+	#[test] fn read_until_stale_mmap() { read_until_stale::<MmapBuffer>() }
+
+	fn peek_until<B: Buffer>()
+	where
+		B::Error: Debug,
+		Error: From<B::Error>,
+	{
+		let mut r = BufRefReaderBuilder::new(CountingReader { src: WORDS, reads: 0 })
+			.capacity(16)
+			.build::<B>()
+			.unwrap();
+		loop {
+			let peeked = r.peek_until(b'\n').unwrap().map(|x| x.to_vec());
+			// peeking twice changes nothing
+			assert_eq!(r.peek_until(b'\n').unwrap().map(|x| x.to_vec()), peeked);
+			let position = r.position();
+			let reads = r.src.reads;
+			let read = r.read_until(b'\n').unwrap().map(|x| x.to_vec());
+			assert_eq!(read, peeked);
+			match read {
+				// past EOF, reader is free to try reading some more
+				None => break,
+				Some(read) => {
+					assert_eq!(r.src.reads, reads);
+					assert_eq!(r.position(), position + read.len() as u64);
+				},
+			}
+		}
+		assert_eq!(r.position(), WORDS.len() as u64);
+	}
+
+	#[test] fn peek_until_vec()  { peek_until::<VecBuffer>() }
+	#[test] fn peek_until_mmap() { peek_until::<MmapBuffer>() }
+
+	#[test]
+	fn read_until_compacted() {
+		let mut r = BufRefReaderBuilder::new(&b"lo
+rem ipsum
+dolor"[..])
+			.capacity(4)
+			.build::<VecBuffer>()
+			.unwrap();
+		assert_eq!(r.read_until(b'\n').unwrap(), Some(&b"lo\n"[..]));
+		assert_eq!(r.buffer_layout(), BufferLayout { capacity: 4, start: 3, len: 1, appendable_len: 0 });
+		// the rest of the token is read after buffer is compacted, and then again after it is reallocated
+		assert_eq!(r.read_until(b'\n').unwrap(), Some(&b"rem ipsum\n"[..]));
+		assert_eq!(r.read_until(b'\n').unwrap(), Some(&b"dolor"[..]));
+		assert_eq!(r.read_until(b'\n').unwrap(), None);
+	}
+
+	fn read_until_bytes<B: Buffer>()
+	where
+		B::Error: Debug,
+		Error: From<B::Error>,
+	{
+		let data = b"lorem<>ipsum<<>>dolor<><>sit<";
+		let expected = [&b"lorem<>"[..], b"ipsum<<>", b">dolor<>", b"<>", b"sit<"];
+
+		let mut r = BufRefReaderBuilder::new(OneByteReader(data))
+			.capacity(4)
+			.build::<B>()
+			.unwrap();
+		for token in &expected {
+			assert_eq!(r.read_until_bytes(b"<>").unwrap(), Some(*token));
+		}
+		assert_eq!(r.read_until_bytes(b"<>").unwrap(), None);
+
+		let mut r = BufRefReaderBuilder::new(OneByteReader(data))
+			.capacity(4)
+			.record_delimiter(b"<>")
+			.build::<B>()
+			.unwrap();
+		assert_eq!(r.read_record().unwrap(), Some(expected[0]));
+		// reuses the same searcher
+		assert_eq!(r.read_until_bytes(b"<>").unwrap(), Some(expected[1]));
+		// builds another one
+		assert_eq!(r.read_until_bytes(b"r").unwrap(), Some(&b">dolor"[..]));
+		assert_eq!(r.read_record().unwrap(), Some(&b"<>"[..]));
+		assert_eq!(r.read_record().unwrap(), Some(expected[3]));
+		assert_eq!(r.read_record().unwrap(), Some(expected[4]));
+		assert_eq!(r.read_record().unwrap(), None);
+	}
+
+	#[test] fn read_until_bytes_vec()  { read_until_bytes::<VecBuffer>() }
+	#[test] fn read_until_bytes_mmap() { read_until_bytes::<MmapBuffer>() }
+
+	fn read_until_bytes_words<B: Buffer>()
+	where
+		B::Error: Debug,
+		Error: From<B::Error>,
+	{
+		let mut r = BufRefReaderBuilder::new(WORDS)
+			.capacity(16)
+			.record_delimiter(b"\na")
+			.build::<B>()
+			.unwrap();
+		let mut rebuilt = vec![];
+		while let Some(record) = r.read_record().unwrap() {
+			assert!(record.ends_with(b"\na") || rebuilt.len() + record.len() == WORDS.len());
+			assert_eq!(memchr::memmem::find(&record[..record.len()-1], b"\na"), None);
+			rebuilt.extend_from_slice(record);
+		}
+		assert_eq!(&rebuilt[..], WORDS);
+	}
+
+	#[test] fn read_until_bytes_words_vec()  { read_until_bytes_words::<VecBuffer>() }
+	#[test] fn read_until_bytes_words_mmap() { read_until_bytes_words::<MmapBuffer>() }
+
+	fn read_until_nth<B: Buffer>()
+	where
+		B::Error: Debug,
+		Error: From<B::Error>,
+	{
+		let mut r = BufRefReaderBuilder::new(OneByteReader(b"lorem\nipsum\n\ndolor\nsit\namet"))
+			.capacity(4)
+			.build::<B>()
+			.unwrap();
+		assert_eq!(r.read_until_nth(b'\n', 3).unwrap(), Some(&b"lorem\nipsum\n\n"[..]));
+		assert_eq!(r.read_until_nth(b'\n', 1).unwrap(), Some(&b"dolor\n"[..]));
+		// fewer lines left
+		assert_eq!(r.read_until_nth(b'\n', 3).unwrap(), Some(&b"sit\namet"[..]));
+		assert_eq!(r.read_until_nth(b'\n', 3).unwrap(), None);
+
+		let mut r = BufRefReaderBuilder::new(WORDS)
+			.capacity(16)
+			.build::<B>()
+			.unwrap();
+		let mut lines = WORDS.split_inclusive(|&c| c == b'\n');
+		while let Some(batch) = r.read_until_nth(b'\n', 7).unwrap() {
+			let expected: Vec<u8> = lines.by_ref().take(7).flatten().copied().collect();
+			assert_eq!(batch, &expected[..]);
+		}
+		assert_eq!(lines.next(), None);
+	}
+
+	#[test] fn read_until_nth_vec()  { read_until_nth::<VecBuffer>() }
+	#[test] fn read_until_nth_mmap() { read_until_nth::<MmapBuffer>() }
+
+	fn read_batch<B: Buffer>()
+	where
+		B::Error: Debug,
+		Error: From<B::Error>,
+	{
+		let mut r = BufRefReaderBuilder::new(OneByteReader(b"lorem\nipsum\ndolor\nsit\namet"))
+			.capacity(4)
+			.build::<B>()
+			.unwrap();
+		// ends at the first delimiter past 8 bytes
+		assert_eq!(r.read_batch(b'\n', 8).unwrap(), Some(&b"lorem\nipsum\n"[..]));
+		// delimiter right at the target
+		assert_eq!(r.read_batch(b'\n', 6).unwrap(), Some(&b"dolor\n"[..]));
+		// short one at EOF
+		assert_eq!(r.read_batch(b'\n', 100).unwrap(), Some(&b"sit\namet"[..]));
+		assert_eq!(r.read_batch(b'\n', 100).unwrap(), None);
+
+		let mut r = BufRefReaderBuilder::new(WORDS)
+			.capacity(16)
+			.build::<B>()
+			.unwrap();
+		let mut rebuilt = vec![];
+		while let Some(batch) = r.read_batch(b'\n', 100).unwrap() {
+			if rebuilt.len() + batch.len() < WORDS.len() {
+				assert!(batch.len() >= 100);
+				assert_eq!(batch.last(), Some(&b'\n'));
+				// and it's the first delimiter past the target
+				assert_eq!(memchr(b'\n', &batch[99..]), Some(batch.len() - 100));
+			}
+			rebuilt.extend_from_slice(batch);
+		}
+		assert_eq!(&rebuilt[..], WORDS);
+	}
+
+	#[test] fn read_batch_vec()  { read_batch::<VecBuffer>() }
+	#[test] fn read_batch_mmap() { read_batch::<MmapBuffer>() }
+
+	fn read_until_joined<B: Buffer>()
+	where
+		B::Error: Debug,
+		Error: From<B::Error>,
+	{
+		// buffer is reallocated and compacted between tokens, but they are still returned as a single slice
+		let mut r = BufRefReaderBuilder::new(OneByteReader(b"\"lorem,ipsum\",\"dolor,sit,amet\""))
+			.capacity(4)
+			.build::<B>()
+			.unwrap();
+		match r.read_until_joined(b',', 2).unwrap() {
+			Some(Cow::Borrowed(data)) => assert_eq!(data, b"\"lorem,ipsum\","),
+			other => panic!("{:?}", other),
+		}
+		match r.read_until_joined(b',', 3).unwrap() {
+			Some(Cow::Borrowed(data)) => assert_eq!(data, b"\"dolor,sit,amet\""),
+			other => panic!("{:?}", other),
+		}
+		assert_eq!(r.read_until_joined(b',', 1).unwrap(), None);
+		assert_eq!(r.stats().tokens, 2);
+
+		// tokens do not fit into the buffer all at once, so they are copied
+		let data = b"lorem,".repeat(2000);
+		let mut r = BufRefReaderBuilder::new(&data[..])
+			.capacity(16)
+			.fixed(true)
+			.build::<B>()
+			.unwrap();
+		match r.read_until_joined(b',', 1999).unwrap() {
+			Some(Cow::Owned(joined)) => assert_eq!(joined, &data[..(1999*6)]),
+			other => panic!("{:?}", other),
+		}
+		assert_eq!(r.position(), 1999*6);
+		assert_eq!(r.read_until_joined(b',', 2).unwrap(), Some(Cow::Borrowed(&b"lorem,"[..])));
+		assert_eq!(r.read_until_joined(b',', 1).unwrap(), None);
+		assert_eq!(r.stats().tokens, 2);
+		assert_eq!(r.max_token_len(), Some(1999*6));
+	}
+
+	#[test] fn read_until_joined_vec()  { read_until_joined::<VecBuffer>() }
+	#[test] fn read_until_joined_mmap() { read_until_joined::<MmapBuffer>() }
+
+	#[test]
+	fn next_token() {
+		let mut r = BufRefReaderBuilder::new(OneByteReader(b"lorem=1\nipsum=22\ndolor=333"))
+			.capacity(4)
+			.build::<VecBuffer>()
+			.unwrap();
+		let mut pairs = vec![];
+		loop {
+			let key = match r.next_token(b'=').unwrap() {
+				None => break,
+				Some(token) => String::from_utf8(token[..(token.len()-1)].to_vec()).unwrap(),
+			};
+			// previous guard is gone, so reader is available again
+			let token = r.next_token(b'\n').unwrap().unwrap();
+			let value: u32 = std::str::from_utf8(&token).unwrap().trim_end().parse().unwrap();
+			pairs.push((key, value));
+		}
+		assert_eq!(pairs, [("lorem".to_string(), 1), ("ipsum".to_string(), 22), ("dolor".to_string(), 333)]);
+
+		let mut r = BufRefReader::<_, VecBuffer>::new(&b"lorem ipsum"[..]).unwrap();
+		let token = r.next_token(b' ').unwrap().unwrap();
+		assert_eq!(token.as_ref(), b"lorem ");
+		assert_eq!(token.into_inner(), b"lorem ");
+		assert_eq!(r.next_token(b' ').unwrap().as_deref(), Some(&b"ipsum"[..]));
+		assert_eq!(r.next_token(b' ').unwrap(), None);
+	}
+
+	fn read<B: Buffer>()
+	where
+		B::Error: Debug,
+		Error: From<B::Error>,
+	{
+		let mut r = BufRefReaderBuilder::new(&b"lorem ipsum dolor sit amet"[..])
+			.capacity(4)
+			.build::<B>()
+			.unwrap();
+		assert_eq!(r.read(5).unwrap(), Some(&b"lorem"[..]));
+		assert_eq!(r.read(6).unwrap(), Some(&b" ipsum"[..]));
+		assert_eq!(r.read(1024).unwrap(), Some(&b" dolor sit amet"[..]));
+		assert_eq!(r.read(1).unwrap(), None);
+	}
+
+	#[test] fn read_vec()  { read::<VecBuffer>() }
+	#[test] fn read_mmap() { read::<MmapBuffer>() }
+
+	fn read_some<B: Buffer>()
+	where
+		B::Error: Debug,
+		Error: From<B::Error>,
+	{
+		let mut r = BufRefReaderBuilder::new(CountingReader { src: WORDS, reads: 0 })
+			.capacity(16)
+			.build::<B>()
+			.unwrap();
+		let cap = r.capacity();
+
+		assert_eq!(r.read_some(4).unwrap(), Some(&WORDS[..4]));
+		assert_eq!(r.src.reads, 1);
+		// whatever is left is returned without reading any more data
+		assert_eq!(r.read_some(cap * 2).unwrap(), Some(&WORDS[4..cap]));
+		assert_eq!(r.src.reads, 1);
+
+		// partial chunk: only as much as a single read could fit
+		assert_eq!(r.read_some(cap * 2).unwrap(), Some(&WORDS[cap..cap*2]));
+		assert_eq!(r.src.reads, 2);
+
+		let mut rebuilt = WORDS[..cap*2].to_vec();
+		while let Some(chunk) = r.read_some(cap * 2).unwrap() {
+			assert!(chunk.len() <= cap);
+			rebuilt.extend_from_slice(chunk);
+		}
+		assert_eq!(&rebuilt[..], WORDS);
+		assert_eq!(r.capacity(), cap);
+	}
+
+	#[test] fn read_some_vec()  { read_some::<VecBuffer>() }
+	#[test] fn read_some_mmap() { read_some::<MmapBuffer>() }
+
+	fn unread<B: Buffer>()
+	where
+		B::Error: Debug,
+		Error: From<B::Error>,
+	{
+		let mut r = BufRefReaderBuilder::new(WORDS)
+			.capacity(16)
+			.build::<B>()
+			.unwrap();
+		let cap = r.capacity();
+
+		assert_eq!(r.read(cap).unwrap(), Some(&WORDS[..cap]));
+		r.unread(4).unwrap();
+		assert_eq!(r.position(), cap as u64 - 4);
+		assert_eq!(r.read(4).unwrap(), Some(&WORDS[cap-4..cap]));
+		r.unread(cap).unwrap();
+		assert_eq!(r.read(cap).unwrap(), Some(&WORDS[..cap]));
+
+		// buffer is out of data, refilling it clobbers everything that was consumed
+		assert_eq!(r.read(1).unwrap(), Some(&WORDS[cap..cap+1]));
+		match r.unread(2) {
+			Err(Error::CannotUnread) => (),
+			x => panic!("expected CannotUnread, got {:?}", x),
+		}
+		assert_eq!(r.position(), cap as u64 + 1);
+		r.unread(1).unwrap();
+		assert_eq!(r.read(2).unwrap(), Some(&WORDS[cap..cap+2]));
+
+		// put back consumed data that wraps around the end of the ring
+		let mut r = BufRefReaderBuilder::new(WORDS)
+			.capacity(16)
+			.build::<B>()
+			.unwrap();
+		let cap = r.capacity();
+		let start = cap/2 + 1;
+		r.read(start).unwrap();
+		assert_eq!(r.read(cap).unwrap(), Some(&WORDS[start..start+cap]));
+		r.unread(cap).unwrap();
+		assert_eq!(r.read(cap).unwrap(), Some(&WORDS[start..start+cap]));
+	}
+
+	#[test] fn unread_vec()  { unread::<VecBuffer>() }
+	#[test] fn unread_mmap() { unread::<MmapBuffer>() }
+
+	fn read_words<B: Buffer>(cap: usize, read: usize)
+	where
+		B::Error: Debug,
+		Error: From<B::Error>,
+	{
+		let mut r = BufRefReaderBuilder::new(WORDS)
+			.capacity(cap)
+			.build::<B>()
+			.unwrap();
+		let mut words = WORDS.chunks(read);
+		while let Ok(Some(slice_buf)) = r.read(read) {
+			let ref_word = words.next().unwrap();
+			assert_eq!(slice_buf, ref_word);
+		}
+		assert_eq!(words.next(), None);
+	}
+
+	#[test] fn read_words_vec_4x3()  { read_words::<VecBuffer>(4, 3) }
+	#[test] fn read_words_vec_4x5()  { read_words::<VecBuffer>(4, 5) }
+	#[test] fn read_words_mmap_4x3() { read_words::<MmapBuffer>(4, 3) }
+	#[test] fn read_words_mmap_4x5() { read_words::<MmapBuffer>(4, 5) }
+
+	fn config<B: Buffer>()
+	where
+		B::Error: Debug,
+		Error: From<B::Error>,
+	{
+		let config = BufRefReaderBuilder::new(&b""[..])
+			.capacity(4)
+			.config();
+		assert_eq!(config.capacity, 4);
+
+		let mut r1 = BufRefReaderBuilder::from_config(WORDS, config.clone())
+			.build::<B>()
+			.unwrap();
+		let mut r2 = BufRefReaderBuilder::from_config(WORDS, config)
+			.build::<B>()
+			.unwrap();
+		loop {
+			let a = r1.read_until(b'\n').unwrap().map(|s| s.to_vec());
+			let b = r2.read_until(b'\n').unwrap();
+			assert_eq!(a.as_deref(), b);
+			if a.is_none() { break }
+		}
+	}
+
+	#[test] fn config_vec()  { config::<VecBuffer>() }
+	#[test] fn config_mmap() { config::<MmapBuffer>() }
+
+	// yields one byte per read() call, forcing the reader to fill its buffer between every two bytes
+	struct OneByteReader<'a>(&'a [u8]);
+	impl Read for OneByteReader<'_> {
+		fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+			let n = std::cmp::min(1, buf.len());
+			self.0.read(&mut buf[..n])
+		}
+	}
+
+	// counts calls to read()
+	struct CountingReader<'a> {
+		src: &'a [u8],
+		reads: usize,
+	}
+	impl Read for CountingReader<'_> {
+		fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+			self.reads += 1;
+			self.src.read(buf)
+		}
+	}
+
+	// fails with Interrupted `interrupts` times before every successful read
+	struct InterruptingReader<'a> {
+		src: &'a [u8],
+		interrupts: usize,
+		left: usize,
+	}
+	impl Read for InterruptingReader<'_> {
+		fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+			if self.left > 0 {
+				self.left -= 1;
+				return Err(io::ErrorKind::Interrupted.into());
+			}
+			self.left = self.interrupts;
+			self.src.read(buf)
+		}
+	}
+
+	fn retry_interrupted<B: Buffer>()
+	where
+		B::Error: Debug,
+		Error: From<B::Error>,
+	{
+		let src = InterruptingReader { src: &b"lorem ipsum dolor"[..], interrupts: 2, left: 2 };
+		let mut r = BufRefReaderBuilder::new(src)
+			.capacity(4)
+			.build::<B>()
+			.unwrap();
+		assert_eq!(r.read_until(b' ').unwrap(), Some(&b"lorem "[..]));
+		assert_eq!(r.read_until(b' ').unwrap(), Some(&b"ipsum "[..]));
+		assert_eq!(r.read_until(b' ').unwrap(), Some(&b"dolor"[..]));
+		assert_eq!(r.read_until(b' ').unwrap(), None);
+
+		let src = InterruptingReader { src: &b"lorem ipsum"[..], interrupts: 0, left: 1 };
+		let mut r = BufRefReaderBuilder::new(src)
+			.capacity(4)
+			.retry_interrupted(false)
+			.build::<B>()
+			.unwrap();
+		match r.read_until(b' ') {
+			Err(Error::IO(e)) => assert_eq!(e.kind(), io::ErrorKind::Interrupted),
+			x => panic!("expected Interrupted, got {:?}", x),
+		}
+		// nothing is lost, just try again
+		assert_eq!(r.read_until(b' ').unwrap(), Some(&b"lorem "[..]));
+	}
+
+	// fails once, after `fail_after` bytes
+	struct FailingReader<'a> {
+		src: &'a [u8],
+		fail_after: Option<usize>,
+	}
+	impl Read for FailingReader<'_> {
+		fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+			match self.fail_after {
+				Some(0) => {
+					self.fail_after = None;
+					Err(io::ErrorKind::ConnectionReset.into())
+				},
+				Some(n) => {
+					let n = std::cmp::min(n, buf.len());
+					let read = self.src.read(&mut buf[..n])?;
+					self.fail_after = Some(self.fail_after.unwrap() - read);
+					Ok(read)
+				},
+				None => self.src.read(buf),
+			}
+		}
+	}
+
+	fn poison_on_error<B: Buffer>()
+	where
+		B::Error: Debug,
+		Error: From<B::Error>,
+	{
+		let src = FailingReader { src: &b"lorem ipsum dolor"[..], fail_after: Some(8) };
+		let mut r = BufRefReaderBuilder::new(src)
+			.capacity(4)
+			.poison_on_error(true)
+			.build::<B>()
+			.unwrap();
+		assert_eq!(r.read_until(b' ').unwrap(), Some(&b"lorem "[..]));
+		match r.read_until(b' ') {
+			Err(Error::IO(e)) => assert_eq!(e.kind(), io::ErrorKind::ConnectionReset),
+			x => panic!("expected IO error, got {:?}", x),
+		}
+		// underlying reader would've returned data at this point
+		match r.read_until(b' ') {
+			Err(Error::Poisoned) => (),
+			x => panic!("expected Poisoned, got {:?}", x),
+		}
+		// data that is already buffered does not need underlying reader
+		assert_eq!(r.read(2).unwrap(), Some(&b"ip"[..]));
+		match r.read(100) {
+			Err(Error::Poisoned) => (),
+			x => panic!("expected Poisoned, got {:?}", x),
+		}
+
+		r.reset(FailingReader { src: &b"sit amet"[..], fail_after: None });
+		assert_eq!(r.read_until(b' ').unwrap(), Some(&b"sit "[..]));
+
+		// without poisoning, reader just carries on
+		let src = FailingReader { src: &b"lorem ipsum dolor"[..], fail_after: Some(8) };
+		let mut r = BufRefReaderBuilder::new(src)
+			.capacity(4)
+			.build::<B>()
+			.unwrap();
+		assert_eq!(r.read_until(b' ').unwrap(), Some(&b"lorem "[..]));
+		assert!(r.read_until(b' ').is_err());
+		assert_eq!(r.read_until(b' ').unwrap(), Some(&b"ipsum "[..]));
+	}
+
+	#[test] fn poison_on_error_vec()  { poison_on_error::<VecBuffer>() }
+	#[test] fn poison_on_error_mmap() { poison_on_error::<MmapBuffer>() }
+
+	fn error_after_tokens<B: Buffer>()
+	where
+		B::Error: Debug,
+		Error: From<B::Error>,
+	{
+		for poison in [false, true] {
+			let src = FailingReader { src: &b"lorem\nipsum\ndolor"[..], fail_after: Some(15) };
+			let mut r = BufRefReaderBuilder::new(src)
+				.capacity(64)
+				.poison_on_error(poison)
+				.build::<B>()
+				.unwrap();
+			assert_eq!(r.read_until(b'\n').unwrap(), Some(&b"lorem\n"[..]));
+			// failing reader is not even touched here
+			assert_eq!(r.read_until(b'\n').unwrap(), Some(&b"ipsum\n"[..]));
+			match r.read_until(b'\n') {
+				Err(Error::IO(e)) => assert_eq!(e.kind(), io::ErrorKind::ConnectionReset),
+				x => panic!("expected IO error, got {:?}", x),
+			}
+			assert_eq!(r.buffered(), b"dol");
+			if poison {
+				assert!(matches!(r.read_until(b'\n'), Err(Error::Poisoned)));
+				assert_eq!(r.buffered(), b"dol");
+			} else {
+				assert_eq!(r.read_until(b'\n').unwrap(), Some(&b"dolor"[..]));
+				assert_eq!(r.read_until(b'\n').unwrap(), None);
+			}
+		}
+	}
+
+	#[test] fn error_after_tokens_vec()  { error_after_tokens::<VecBuffer>() }
+	#[test] fn error_after_tokens_mmap() { error_after_tokens::<MmapBuffer>() }
+
+	#[test] fn retry_interrupted_vec()  { retry_interrupted::<VecBuffer>() }
+	#[test] fn retry_interrupted_mmap() { retry_interrupted::<MmapBuffer>() }
+
+	fn reset<B: Buffer>()
+	where
+		B::Error: Debug,
+		Error: From<B::Error>,
+	{
+		let mut r = BufRefReaderBuilder::new(&b"lorem ipsum\ndolor"[..])
+			.capacity(16)
+			.build::<B>()
+			.unwrap();
+		let cap = r.capacity();
+		assert_eq!(r.read_until(b' ').unwrap(), Some(&b"lorem "[..]));
+
+		// "ipsum\ndolor" is still buffered at this point
+		r.reset(&b"sit\namet"[..]);
+		assert_eq!(r.position(), 0);
+		assert_eq!(r.read_until(b'\n').unwrap(), Some(&b"sit\n"[..]));
+		assert_eq!(r.read_until(b'\n').unwrap(), Some(&b"amet"[..]));
+		assert_eq!(r.read_until(b'\n').unwrap(), None);
+		assert_eq!(r.position(), 8);
+		assert_eq!(r.capacity(), cap);
+	}
+
+	#[test] fn reset_vec()  { reset::<VecBuffer>() }
+	#[test] fn reset_mmap() { reset::<MmapBuffer>() }
+
+	fn prefetch<B: Buffer>()
+	where
+		B::Error: Debug,
+		Error: From<B::Error>,
+	{
+		let mut r = BufRefReaderBuilder::new(CountingReader { src: WORDS, reads: 0 })
+			.capacity(16)
+			.build::<B>()
+			.unwrap();
+		assert!(r.prefetch(10000).unwrap() >= 10000);
+		let reads = r.src.reads;
+
+		let mut words = WORDS.split(|&c| c == b'\n');
+		let mut total = 0;
+		while total < 9000 {
+			let word = words.next().unwrap();
+			let line = r.read_until(b'\n').unwrap().unwrap();
+			assert_eq!(&line[..word.len()], word);
+			total += line.len();
+		}
+		assert_eq!(r.src.reads, reads);
+
+		// nothing to do, it's already there
+		r.prefetch(100).unwrap();
+		assert_eq!(r.src.reads, reads);
+
+		let mut r = BufRefReaderBuilder::new(&b"lorem ipsum"[..])
+			.capacity(16)
+			.build::<B>()
+			.unwrap();
+		assert_eq!(r.prefetch(10000).unwrap(), 11);
+		assert_eq!(r.read(100).unwrap(), Some(&b"lorem ipsum"[..]));
+	}
+
+	fn count_until_eof<B: Buffer>()
+	where
+		B::Error: Debug,
+		Error: From<B::Error>,
+	{
+		let mut r = BufRefReaderBuilder::new(WORDS)
+			.capacity(16)
+			.build::<B>()
+			.unwrap();
+		let lines = WORDS.iter().filter(|&&c| c == b'\n').count() as u64;
+		assert_eq!(r.count_until_eof(b'\n').unwrap(), lines);
+		assert_eq!(r.position(), WORDS.len() as u64);
+		assert_eq!(r.count_until_eof(b'\n').unwrap(), 0);
+		assert_eq!(r.read_until(b'\n').unwrap(), None);
+
+		// whatever is buffered already is counted as well
+		let mut r = BufRefReaderBuilder::new(&b"lorem\nipsum\ndolor\n"[..])
+			.capacity(4)
+			.build::<B>()
+			.unwrap();
+		assert_eq!(r.read_until(b'\n').unwrap(), Some(&b"lorem\n"[..]));
+		assert_eq!(r.count_until_eof(b'\n').unwrap(), 2);
+	}
+
+	#[test] fn count_until_eof_vec()  { count_until_eof::<VecBuffer>() }
+	#[test] fn count_until_eof_mmap() { count_until_eof::<MmapBuffer>() }
+
+	fn try_read_until<B: Buffer>()
+	where
+		B::Error: Debug,
+		Error: From<B::Error>,
+	{
+		let mut r = BufRefReaderBuilder::new(CountingReader { src: &b"lorem\nipsum\ndolor"[..], reads: 0 })
+			.capacity(8)
+			.build::<B>()
+			.unwrap();
+		match r.try_read_until(b'\n') {
+			Err(Error::Incomplete) => (),
+			x => panic!("expected Incomplete, got {:?}", x),
+		}
+		assert_eq!(r.src.reads, 0);
+
+		r.prefetch(8).unwrap();
+		let reads = r.src.reads;
+		assert_eq!(r.try_read_until(b'\n').unwrap(), Some(&b"lorem\n"[..]));
+		assert_eq!(r.src.reads, reads);
+
+		r.prefetch(100).unwrap();
+		let reads = r.src.reads;
+		assert_eq!(r.try_read_until(b'\n').unwrap(), Some(&b"ipsum\n"[..]));
+		// EOF was already reported by prefetch()
+		assert_eq!(r.try_read_until(b'\n').unwrap(), Some(&b"dolor"[..]));
+		assert_eq!(r.try_read_until(b'\n').unwrap(), None);
+		assert_eq!(r.src.reads, reads);
+	}
+
+	#[test] fn try_read_until_vec()  { try_read_until::<VecBuffer>() }
+	#[test] fn try_read_until_mmap() { try_read_until::<MmapBuffer>() }
+
+	fn delim_pos<B: Buffer>()
+	where
+		B::Error: Debug,
+		Error: From<B::Error>,
+	{
+		let mut r1 = BufRefReaderBuilder::new(WORDS)
+			.capacity(8)
+			.build::<B>()
+			.unwrap();
+		let mut r2 = BufRefReaderBuilder::new(WORDS)
+			.capacity(8)
+			.build::<B>()
+			.unwrap();
+		while let Some(pos) = r1.delim_pos(b'\n').unwrap() {
+			// nothing is consumed until read
+			assert_eq!(r1.delim_pos(b'\n').unwrap(), Some(pos));
+			let word = r1.read(pos+1).unwrap();
+			assert_eq!(word, r2.read_until(b'\n').unwrap());
+		}
+		assert_eq!(r2.read_until(b'\n').unwrap(), None);
+
+		let mut r = BufRefReaderBuilder::new(&b"lorem\nipsum"[..])
+			.capacity(4)
+			.build::<B>()
+			.unwrap();
+		assert_eq!(r.delim_pos(b'\n').unwrap(), Some(5));
+		assert_eq!(r.read(6).unwrap(), Some(&b"lorem\n"[..]));
+		// EOF, remaining data is untouched
+		assert_eq!(r.delim_pos(b'\n').unwrap(), None);
+		assert_eq!(r.read_until(b'\n').unwrap(), Some(&b"ipsum"[..]));
+	}
+
+	#[test] fn delim_pos_vec()  { delim_pos::<VecBuffer>() }
+	#[test] fn delim_pos_mmap() { delim_pos::<MmapBuffer>() }
+
+	fn copy_until<B: Buffer>()
+	where
+		B::Error: Debug,
+		Error: From<B::Error>,
+	{
+		let mut r = BufRefReaderBuilder::new(WORDS)
+			.capacity(16)
+			.build::<B>()
+			.unwrap();
+		let mut out = vec![];
+		let mut lines = WORDS.split_inclusive(|&c| c == b'\n');
+		while let Some(len) = r.copy_until(b'\n', &mut out).unwrap() {
+			assert_eq!(Some(len), lines.next().map(|line| line.len()));
+		}
+		assert_eq!(out, WORDS);
+		assert_eq!(r.position(), WORDS.len() as u64);
+
+		// token is not lost if sink fails
+		let mut r = BufRefReaderBuilder::new(&b"lorem\nipsum"[..])
+			.build::<B>()
+			.unwrap();
+		let mut full = [0; 3];
+		match r.copy_until(b'\n', &mut &mut full[..]) {
+			Err(Error::Write(err)) => assert_eq!(err.kind(), io::ErrorKind::WriteZero),
+			x => panic!("expected Write, got {:?}", x),
+		}
+		let mut out = vec![];
+		assert_eq!(r.copy_until(b'\n', &mut out).unwrap(), Some(6));
+		assert_eq!(r.copy_until(b'\n', &mut out).unwrap(), Some(5));
+		assert_eq!(r.copy_until(b'\n', &mut out).unwrap(), None);
+		assert_eq!(out, b"lorem\nipsum");
+	}
+
+	#[test] fn copy_until_vec()  { copy_until::<VecBuffer>() }
+	#[test] fn copy_until_mmap() { copy_until::<MmapBuffer>() }
+
+	#[test] fn prefetch_vec()  { prefetch::<VecBuffer>() }
+	#[test] fn prefetch_mmap() { prefetch::<MmapBuffer>() }
+
+	#[test]
+	fn prefetch_fixed() {
+		let mut r = BufRefReaderBuilder::new(WORDS)
+			.capacity(16)
+			.fixed(true)
+			.build::<VecBuffer>()
+			.unwrap();
+		assert_eq!(r.prefetch(10000).unwrap(), 16);
+	}
+
+	fn sniff<B: Buffer>()
+	where
+		B::Error: Debug,
+		Error: From<B::Error>,
+	{
+		let mut r = BufRefReaderBuilder::new(OneByteReader(WORDS))
+			.capacity(16)
+			.build::<B>()
+			.unwrap();
+		assert_eq!(r.sniff(512).unwrap(), &WORDS[..512]);
+		// sniffing twice changes nothing
+		assert_eq!(r.sniff(4).unwrap(), &WORDS[..4]);
+		assert_eq!(r.position(), 0);
+
+		let mut rebuilt = vec![];
+		while let Some(chunk) = r.read(100).unwrap() {
+			rebuilt.extend_from_slice(chunk);
+		}
+		assert_eq!(rebuilt, WORDS);
+
+		// short stream
+		let mut r = BufRefReaderBuilder::new(&b"lorem"[..])
+			.build::<B>()
+			.unwrap();
+		assert_eq!(r.sniff(512).unwrap(), b"lorem");
+		assert_eq!(r.read_until(b'\n').unwrap(), Some(&b"lorem"[..]));
+	}
+
+	#[test] fn sniff_vec()  { sniff::<VecBuffer>() }
+	#[test] fn sniff_mmap() { sniff::<MmapBuffer>() }
+
+	fn read_line<B: Buffer>()
+	where
+		B::Error: Debug,
+		Error: From<B::Error>,
+	{
+		let data = b"\nlorem\r\n\r\nipsum\rdolor\n\n\r\nsit\r";
+		let mut r = BufRefReaderBuilder::new(OneByteReader(data))
+			.capacity(1)
+			.build::<B>()
+			.unwrap();
+		assert_eq!(r.read_line().unwrap(), Some(&b""[..]));
+		assert_eq!(r.read_line().unwrap(), Some(&b"lorem"[..]));
+		assert_eq!(r.read_line().unwrap(), Some(&b""[..]));
+		assert_eq!(r.read_line().unwrap(), Some(&b"ipsum\rdolor"[..]));
+		assert_eq!(r.read_line().unwrap(), Some(&b""[..]));
+		assert_eq!(r.read_line().unwrap(), Some(&b""[..]));
+		assert_eq!(r.read_line().unwrap(), Some(&b"sit\r"[..]));
+		assert_eq!(r.read_line().unwrap(), None);
+	}
+
+	#[test] fn read_line_vec()  { read_line::<VecBuffer>() }
+	#[test] fn read_line_mmap() { read_line::<MmapBuffer>() }
+
+	fn read_line_with_ending<B: Buffer>()
+	where
+		B::Error: Debug,
+		Error: From<B::Error>,
+	{
+		let data = b"\nlorem\r\n\r\nipsum\rdolor\n\n\r\nsit\r";
+		let mut r = BufRefReaderBuilder::new(OneByteReader(data))
+			.capacity(1)
+			.build::<B>()
+			.unwrap();
+		let mut rebuilt = vec![];
+		let mut lines = vec![];
+		while let Some((line, ending)) = r.read_line_with_ending().unwrap() {
+			rebuilt.extend_from_slice(line);
+			rebuilt.extend_from_slice(match ending {
+				LineEnding::Lf => b"\n",
+				LineEnding::CrLf => b"\r\n",
+				LineEnding::None => b"",
+			});
+			lines.push((line.to_vec(), ending));
+		}
+		assert_eq!(lines, vec![
+			(b"".to_vec(), LineEnding::Lf),
+			(b"lorem".to_vec(), LineEnding::CrLf),
+			(b"".to_vec(), LineEnding::CrLf),
+			(b"ipsum\rdolor".to_vec(), LineEnding::Lf),
+			(b"".to_vec(), LineEnding::Lf),
+			(b"".to_vec(), LineEnding::CrLf),
+			(b"sit\r".to_vec(), LineEnding::None),
+		]);
+		assert_eq!(rebuilt, data);
+	}
+
+	#[test] fn read_line_with_ending_vec()  { read_line_with_ending::<VecBuffer>() }
+	#[test] fn read_line_with_ending_mmap() { read_line_with_ending::<MmapBuffer>() }
+
+	fn lines_owned<B: Buffer>()
+	where
+		B::Error: Debug,
+		Error: From<B::Error>,
+	{
+		let lines = BufRefReaderBuilder::new(WORDS)
+			.capacity(16)
+			.build::<B>()
+			.unwrap()
+			.lines_owned();
+		let mut r = BufRefReaderBuilder::new(WORDS)
+			.capacity(16)
+			.build::<B>()
+			.unwrap();
+		for line in lines {
+			let word = r.read_until(b'\n').unwrap().unwrap();
+			assert_eq!(line.unwrap(), word.strip_suffix(b"\n").unwrap_or(word));
+		}
+		assert_eq!(r.read_until(b'\n').unwrap(), None);
+	}
+
+	#[test] fn lines_owned_vec()  { lines_owned::<VecBuffer>() }
+	#[test] fn lines_owned_mmap() { lines_owned::<MmapBuffer>() }
+
+	fn records<B: Buffer>()
+	where
+		B::Error: Debug,
+		Error: From<B::Error>,
+	{
+		let build = |data| BufRefReaderBuilder::new(OneByteReader(data))
+			.capacity(4)
+			.build::<B>()
+			.unwrap();
+
+		let records: Vec<[u8; 3]> = build(b"loremipsum dolor")
+			.records()
+			.take_while(Result::is_ok)
+			.map(Result::unwrap)
+			.collect();
+		assert_eq!(records, vec![*b"lor", *b"emi", *b"psu", *b"m d", *b"olo"]);
+
+		let records: Vec<[u8; 4]> = build(b"loremipsum dolor")
+			.records()
+			.map(Result::unwrap)
+			.collect();
+		assert_eq!(records, vec![*b"lore", *b"mips", *b"um d", *b"olor"]);
+
+		// partial record is the last one
+		let mut records = build(b"loremipsum dolor").records::<3>().skip(5);
+		match records.next() {
+			Some(Err(Error::IO(err))) => assert_eq!(err.kind(), io::ErrorKind::UnexpectedEof),
+			x => panic!("expected UnexpectedEof, got {:?}", x),
+		}
+		assert!(records.next().is_none());
+
+		assert_eq!(build(b"").records::<3>().count(), 0);
+	}
+
+	#[test] fn records_vec()  { records::<VecBuffer>() }
+	#[test] fn records_mmap() { records::<MmapBuffer>() }
+
+	fn split_owned<B: Buffer>()
+	where
+		B::Error: Debug,
+		Error: From<B::Error>,
+	{
+		fn tokens(it: impl Iterator<Item = Result<Vec<u8>, Error>>) -> Vec<Vec<u8>> {
+			it.map(|token| token.unwrap()).collect()
+		}
+		let build = |data: &'static [u8]| BufRefReaderBuilder::new(data)
+			.capacity(4)
+			.build::<B>()
+			.unwrap();
+
+		for data in &[&b"lorem\nipsum\n\ndolor\n"[..], &b"lorem\nipsum\n\ndolor"[..], &b"\n"[..], &b""[..]] {
+			let expected: Vec<Vec<u8>> = data.split(|&c| c == b'\n').map(|s| s.to_vec()).collect();
+			assert_eq!(tokens(build(data).split_owned(b'\n')), expected);
+		}
+
+		// no trailing empty token past the last b'\n'
+		assert_eq!(
+			tokens(build(b"lorem\nipsum\n\ndolor\n").split_terminator_owned(b'\n')),
+			vec![&b"lorem"[..], b"ipsum", b"", b"dolor"],
+		);
+		// final partial token
+		assert_eq!(
+			tokens(build(b"lorem\nipsum\n\ndolor").split_terminator_owned(b'\n')),
+			vec![&b"lorem"[..], b"ipsum", b"", b"dolor"],
+		);
+		assert_eq!(tokens(build(b"\n").split_terminator_owned(b'\n')), vec![&b""[..]]);
+		assert!(tokens(build(b"").split_terminator_owned(b'\n')).is_empty());
+		assert_eq!(tokens(build(b"lorem\n").lines_owned()), vec![&b"lorem"[..]]);
+	}
+
+	#[test] fn split_owned_vec()  { split_owned::<VecBuffer>() }
+	#[test] fn split_owned_mmap() { split_owned::<MmapBuffer>() }
+
+	fn auto_shrink<B: Buffer>()
+	where
+		B::Error: Debug,
+		Error: From<B::Error>,
+	{
+		let mut data = vec![b'x'; 100_000];
+		data.push(b'\n');
+		for _ in 0..1000 {
+			data.extend_from_slice(b"lorem\n");
+		}
+
+		let mut r = BufRefReaderBuilder::new(OneByteReader(&data))
+			.capacity(16)
+			.auto_shrink(AutoShrink::Below(64*1024))
+			.build::<B>()
+			.unwrap();
+		let initial = r.capacity();
+
+		assert_eq!(r.read_until(b'\n').unwrap().map(|s| s.len()), Some(100_001));
+		assert!(r.capacity() > 64*1024);
+
+		while let Some(line) = r.read_until(b'\n').unwrap() {
+			assert_eq!(line, b"lorem\n");
+			assert_eq!(r.capacity(), initial);
+		}
+	}
+
+	fn read_logical_line<B: Buffer>()
+	where
+		B::Error: Debug,
+		Error: From<B::Error>,
+	{
+		let data = b"a = 1\nb = 2 \\\n  3 \\\r\n  4\r\n\\\nc = \\r\\\n\\";
+		let mut r = BufRefReaderBuilder::new(OneByteReader(data))
+			.capacity(1)
+			.build::<B>()
+			.unwrap();
+		assert_eq!(r.read_logical_line(b'\\').unwrap(), Some(&b"a = 1"[..]));
+		// three physical lines
+		assert_eq!(r.read_logical_line(b'\\').unwrap(), Some(&b"b = 2   3   4"[..]));
+		assert_eq!(r.position(), 26);
+		assert_eq!(r.read_logical_line(b'\\').unwrap(), Some(&b"c = \\r"[..]));
+		assert_eq!(r.read_logical_line(b'\\').unwrap(), None);
+		assert_eq!(r.position(), data.len() as u64);
+
+		// continuation right before EOF
+		let mut r = BufRefReaderBuilder::new(&b"lorem\\\n"[..])
+			.capacity(16)
+			.build::<B>()
+			.unwrap();
+		assert_eq!(r.read_logical_line(b'\\').unwrap(), Some(&b"lorem"[..]));
+		assert_eq!(r.read_logical_line(b'\\').unwrap(), None);
+	}
+
+	#[test] fn read_logical_line_vec()  { read_logical_line::<VecBuffer>() }
+	#[test] fn read_logical_line_mmap() { read_logical_line::<MmapBuffer>() }
+
+	#[test] fn auto_shrink_vec()  { auto_shrink::<VecBuffer>() }
+	#[test] fn auto_shrink_mmap() { auto_shrink::<MmapBuffer>() }
+
+	// matches commas and newlines outside of quoted parts of CSV fields
+	fn csv_delim() -> impl FnMut(&[u8], usize) -> bool {
+		let mut quoted = false;
+		move |data, i| match data[i] {
+			// escaped quote (`""`) toggles this twice, leaving us within quoted part
+			b'"' => { quoted = !quoted; false },
+			b',' | b'\n' => !quoted,
+			_ => false,
+		}
+	}
+
+	fn read_until_fn<B: Buffer>()
+	where
+		B::Error: Debug,
+		Error: From<B::Error>,
+	{
+		let data = b"a,\"b,c\",\"d\"\"e\",\"\"\nf\n\"g\nh\"";
+		let mut r = BufRefReaderBuilder::new(OneByteReader(data))
+			.capacity(1)
+			.build::<B>()
+			.unwrap();
+		assert_eq!(r.read_until_fn(csv_delim()).unwrap(), Some(&b"a,"[..]));
+		assert_eq!(r.read_until_fn(csv_delim()).unwrap(), Some(&b"\"b,c\","[..]));
+		assert_eq!(r.read_until_fn(csv_delim()).unwrap(), Some(&b"\"d\"\"e\","[..]));
+		assert_eq!(r.read_until_fn(csv_delim()).unwrap(), Some(&b"\"\"\n"[..]));
+		assert_eq!(r.read_until_fn(csv_delim()).unwrap(), Some(&b"f\n"[..]));
+		assert_eq!(r.read_until_fn(csv_delim()).unwrap(), Some(&b"\"g\nh\""[..]));
+		assert_eq!(r.read_until_fn(csv_delim()).unwrap(), None);
+	}
+
+	#[test] fn read_until_fn_vec()  { read_until_fn::<VecBuffer>() }
+	#[test] fn read_until_fn_mmap() { read_until_fn::<MmapBuffer>() }
+
+	fn read_record_offsets<B: Buffer>()
+	where
+		B::Error: Debug,
+		Error: From<B::Error>,
+	{
+		let mut r = BufRefReaderBuilder::new(OneByteReader(b"lorem,,ipsum,\n,dolor\nsit"))
+			.capacity(4)
+			.build::<B>()
+			.unwrap();
+
+		let (record, offsets) = r.read_record_offsets(b'\n', b',').unwrap().unwrap();
+		assert_eq!(record, b"lorem,,ipsum,\n");
+		assert_eq!(&offsets[..], &[5, 6, 12]);
+		assert!(!offsets.spilled());
+		let mut start = 0;
+		let mut fields = vec![];
+		for &end in offsets.iter().chain(Some(&(record.len() - 1))) {
+			fields.push(&record[start..end]);
+			start = end + 1;
+		}
+		assert_eq!(fields, vec![&b"lorem"[..], b"", b"ipsum", b""]);
+
+		let (record, offsets) = r.read_record_offsets(b'\n', b',').unwrap().unwrap();
+		assert_eq!(record, b",dolor\n");
+		assert_eq!(&offsets[..], &[0]);
+
+		let (record, offsets) = r.read_record_offsets(b'\n', b',').unwrap().unwrap();
+		assert_eq!(record, b"sit");
+		assert!(offsets.is_empty());
+
+		assert!(r.read_record_offsets(b'\n', b',').unwrap().is_none());
+	}
+
+	#[test] fn read_record_offsets_vec()  { read_record_offsets::<VecBuffer>() }
+	#[test] fn read_record_offsets_mmap() { read_record_offsets::<MmapBuffer>() }
+
+	fn read_until_range<B: Buffer>()
+	where
+		B::Error: Debug,
+		Error: From<B::Error>,
+	{
+		let mut r = BufRefReaderBuilder::new(WORDS)
+			.capacity(16)
+			.build::<B>()
+			.unwrap();
+		let mut words = WORDS.split(|&c| c == b'\n');
+
+		let word = words.next().unwrap();
+		let range = r.read_until_range(b'\n').unwrap().unwrap();
+		assert_eq!(range, 0 .. (word.len() + 1) as u64);
+		assert_eq!(r.position(), range.end);
+		assert_eq!(r.slice(range.clone()).unwrap(), &WORDS[..word.len() + 1]);
+
+		// keep reading until the buffer runs out of data and gets refilled
+		let mut evicted = false;
+		while let Some(next) = r.read_until_range(b'\n').unwrap() {
+			assert_eq!(r.slice(next.clone()).unwrap(), &WORDS[next.start as usize .. next.end as usize]);
+			match r.slice(range.clone()) {
+				Some(data) => {
+					assert!(!evicted);
+					assert_eq!(data, &WORDS[..word.len() + 1]);
+				},
+				None => evicted = true,
+			}
+			if evicted { break }
+		}
+		assert!(evicted);
+		assert_eq!(r.slice(range), None);
+	}
+
+	#[test] fn read_until_range_vec()  { read_until_range::<VecBuffer>() }
+	#[test] fn read_until_range_mmap() { read_until_range::<MmapBuffer>() }
+
+	fn read_chunked<B: Buffer>()
+	where
+		B::Error: Debug,
+		Error: From<B::Error>,
+	{
+		let mut r = BufRefReaderBuilder::new(&b"lorem ipsum dolor sit amet\nfoo\nbar"[..])
+			.capacity(4)
+			.build::<B>()
+			.unwrap();
+		assert_eq!(r.read_chunked(b'\n', 10).unwrap(), Some((&b"lorem ipsu"[..], true)));
+		assert_eq!(r.read_chunked(b'\n', 10).unwrap(), Some((&b"m dolor si"[..], true)));
+		assert_eq!(r.read_chunked(b'\n', 10).unwrap(), Some((&b"t amet\n"[..], false)));
+		assert_eq!(r.read_chunked(b'\n', 10).unwrap(), Some((&b"foo\n"[..], false)));
+		assert_eq!(r.read_chunked(b'\n', 2).unwrap(), Some((&b"ba"[..], true)));
+		assert_eq!(r.read_chunked(b'\n', 2).unwrap(), Some((&b"r"[..], false)));
+		assert_eq!(r.read_chunked(b'\n', 2).unwrap(), None);
+	}
+
+	#[test] fn read_chunked_vec()  { read_chunked::<VecBuffer>() }
+	#[test] fn read_chunked_mmap() { read_chunked::<MmapBuffer>() }
+
+	#[test]
+	#[should_panic(expected = "chunk_max must be larger than 0")]
+	fn read_chunked_zero() {
+		// empty chunk would be indistinguishable from EOF
+		let mut r = BufRefReader::<_, VecBuffer>::new(&b"lorem\nipsum"[..]).unwrap();
+		let _ = r.read_chunked(b'\n', 0);
+	}
+
+	fn read_until_or_max<B: Buffer>()
+	where
+		B::Error: Debug,
+		Error: From<B::Error>,
+	{
+		let mut r = BufRefReaderBuilder::new(OneByteReader(b"GET a\r\nSET bb\r\nSET ccccccccc\r\nDEL"))
+			.capacity(4)
+			.build::<B>()
+			.unwrap();
+		// short one
+		assert_eq!(r.read_until_or_max(b'\n', 8).unwrap(), Some((&b"GET a\r\n"[..], true)));
+		// exactly at max, delimiter included
+		assert_eq!(r.read_until_or_max(b'\n', 8).unwrap(), Some((&b"SET bb\r\n"[..], true)));
+		// too long, the rest is still there
+		assert_eq!(r.read_until_or_max(b'\n', 8).unwrap(), Some((&b"SET cccc"[..], false)));
+		assert_eq!(r.read_until_or_max(b'\n', 8).unwrap(), Some((&b"ccccc\r\n"[..], true)));
+		// cut short by EOF
+		assert_eq!(r.read_until_or_max(b'\n', 8).unwrap(), Some((&b"DEL"[..], false)));
+		assert_eq!(r.read_until_or_max(b'\n', 8).unwrap(), None);
+	}
+
+	#[test] fn read_until_or_max_vec()  { read_until_or_max::<VecBuffer>() }
+	#[test] fn read_until_or_max_mmap() { read_until_or_max::<MmapBuffer>() }
+
+	#[test]
+	#[should_panic(expected = "max must be larger than 0")]
+	fn read_until_or_max_zero() {
+		let mut r = BufRefReader::<_, VecBuffer>::new(&b"lorem\nipsum"[..]).unwrap();
+		let _ = r.read_until_or_max(b'\n', 0);
+	}
+
+	fn read_overlapping<B: Buffer>()
+	where
+		B::Error: Debug,
+		Error: From<B::Error>,
+	{
+		let mut r = BufRefReaderBuilder::new(&b"lorem ipsum dolor"[..])
+			.capacity(4)
+			.build::<B>()
+			.unwrap();
+		assert_eq!(r.read_overlapping(6, 2).unwrap(), Some(&b"lorem "[..]));
+		assert_eq!(r.read_overlapping(6, 2).unwrap(), Some(&b"m ipsu"[..]));
+		assert_eq!(r.read_overlapping(6, 2).unwrap(), Some(&b"sum do"[..]));
+		assert_eq!(r.read_overlapping(6, 2).unwrap(), Some(&b"dolor"[..]));
+		assert_eq!(r.read_overlapping(6, 2).unwrap(), None);
+	}
+
+	#[test] fn read_overlapping_vec()  { read_overlapping::<VecBuffer>() }
+	#[test] fn read_overlapping_mmap() { read_overlapping::<MmapBuffer>() }
+
+	fn read_overlapping_words<B: Buffer>(chunk: usize, overlap: usize)
+	where
+		B::Error: Debug,
+		Error: From<B::Error>,
+	{
+		let mut r = BufRefReaderBuilder::new(WORDS)
+			.capacity(4)
+			.build::<B>()
+			.unwrap();
+		let mut prev: Option<Vec<u8>> = None;
+		let mut rebuilt = vec![];
+		while let Some(slice_buf) = r.read_overlapping(chunk, overlap).unwrap() {
+			match prev {
+				None => rebuilt.extend_from_slice(slice_buf),
+				Some(prev) => {
+					// tail of previous chunk is repeated at the front of this one
+					assert_eq!(&prev[prev.len()-overlap..], &slice_buf[..overlap]);
+					rebuilt.extend_from_slice(&slice_buf[overlap..]);
+				},
+			}
+			prev = Some(slice_buf.to_vec());
+		}
+		assert_eq!(&rebuilt[..], WORDS);
+	}
+
+	#[test] fn read_overlapping_words_vec()  { read_overlapping_words::<VecBuffer>(4096, 100) }
+	#[test] fn read_overlapping_words_mmap() { read_overlapping_words::<MmapBuffer>(5000, 1000) }
+
+	fn fixed<B: Buffer>()
+	where
+		B::Error: Debug,
+		Error: From<B::Error>,
+	{
+		let mut data = vec![];
+		for _ in 0..1000 {
+			data.extend_from_slice(b"lorem\n");
+		}
+		data.extend_from_slice(&[b'x'; 10000]);
+		data.push(b'\n');
+
+		let mut r = BufRefReaderBuilder::new(&data[..])
+			.capacity(16)
+			.fixed(true)
+			.build::<B>()
+			.unwrap();
+		let cap = r.capacity();
+
+		// short lines only need buffer to be compacted
+		for _ in 0..1000 {
+			assert_eq!(r.read_until(b'\n').unwrap(), Some(&b"lorem\n"[..]));
+		}
+		match r.read_until(b'\n') {
+			Err(Error::BufferFull) => (),
+			x => panic!("expected BufferFull, got {:?}", x),
+		}
+		assert_eq!(r.capacity(), cap);
+	}
+
+	fn no_progress<B: Buffer>()
+	where
+		B::Error: Debug,
+		Error: From<B::Error>,
+	{
+		// builder rejects zero capacity, but buffer might still end up being that small
+		let mut r = BufRefReaderBuilder::new(CountingReader { src: &b"lorem\nipsum"[..], reads: 0 })
+			.build_with(B::new(0).unwrap());
+		match r.read_until(b'\n') {
+			Err(Error::NoProgress) => (),
+			x => panic!("expected NoProgress, got {:?}", x),
+		}
+		// no data is mistaken for EOF
+		assert_eq!(r.src.reads, 0);
+		assert!(!r.eof);
+	}
+
+	#[test] fn no_progress_vec() { no_progress::<VecBuffer>() }
+
+	#[test] fn fixed_vec()  { fixed::<VecBuffer>() }
+	#[test] fn fixed_mmap() { fixed::<MmapBuffer>() }
+
+	#[test]
+	fn exact_capacity() {
+		let r = BufRefReaderBuilder::new(&b""[..])
+			.capacity(1000)
+			.exact_capacity(true)
+			.build::<VecBuffer>()
+			.unwrap();
+		assert_eq!(r.capacity(), 1000);
+
+		// mmap can only get as close as page size allows
+		let page = vmap::allocation_size();
+		let r = BufRefReaderBuilder::new(&b""[..])
+			.capacity(page + 1)
+			.exact_capacity(true)
+			.build::<MmapBuffer>()
+			.unwrap();
+		assert_eq!(r.capacity(), page * 2);
+	}
+
+	#[test]
+	fn presets() {
+		let r = BufRefReaderBuilder::small(&b""[..])
+			.build::<VecBuffer>()
+			.unwrap();
+		assert_eq!(r.capacity(), 4*1024);
+
+		let r = BufRefReaderBuilder::large(&b""[..])
+			.build::<VecBuffer>()
+			.unwrap();
+		assert_eq!(r.capacity(), 256*1024);
+
+		// presets can be tweaked further
+		let r = BufRefReaderBuilder::large(&b""[..])
+			.fixed(true)
+			.build::<MmapBuffer>()
+			.unwrap();
+		assert_eq!(r.capacity(), 256*1024);
+		assert!(r.config.fixed);
+	}
+
+	fn effective_capacity<B: Buffer>()
+	where
+		B::Error: Debug,
+		Error: From<B::Error>,
+	{
+		for cap in &[1, 1000, 4096, 5000] {
+			let builder = BufRefReaderBuilder::new(&b""[..])
+				.capacity(*cap);
+			let expected = builder.effective_capacity::<B>();
+			assert!(expected >= *cap);
+			assert_eq!(builder.build::<B>().unwrap().capacity(), expected);
+		}
+	}
+
+	#[test] fn effective_capacity_vec()  { effective_capacity::<VecBuffer>() }
+	#[test] fn effective_capacity_mmap() { effective_capacity::<MmapBuffer>() }
+
+	#[cfg(feature = "bytemuck")]
+	#[test]
+	fn read_pod() {
+		#[repr(C)]
+		#[derive(Clone, Copy, Debug, PartialEq)]
+		struct Point {
+			x: u16,
+			y: u16,
+			tag: [u8; 4],
+		}
+		unsafe impl bytemuck::Zeroable for Point {}
+		unsafe impl bytemuck::Pod for Point {}
+
+		let mut data = vec![];
+		for &(x, y, tag) in &[(1u16, 2u16, b"lore"), (3, 4, b"mips")] {
+			data.extend_from_slice(&x.to_ne_bytes());
+			data.extend_from_slice(&y.to_ne_bytes());
+			data.extend_from_slice(tag);
+		}
+		// cut short
+		data.extend_from_slice(b"dolor");
+
+		let mut r = BufRefReaderBuilder::new(&data[..])
+			// not a multiple of size_of::<Point>()
+			.capacity(5)
+			.build::<VecBuffer>()
+			.unwrap();
+		assert_eq!(r.read_pod::<Point>().unwrap(), Some(Point { x: 1, y: 2, tag: *b"lore" }));
+		assert_eq!(r.read_pod::<Point>().unwrap(), Some(Point { x: 3, y: 4, tag: *b"mips" }));
+		match r.read_pod::<Point>() {
+			Err(Error::IO(err)) => assert_eq!(err.kind(), io::ErrorKind::UnexpectedEof),
+			x => panic!("expected UnexpectedEof, got {:?}", x),
+		}
+		assert_eq!(r.read_pod::<Point>().unwrap(), None);
+	}
+
+	fn readahead<B: Buffer>()
+	where
+		B::Error: Debug,
+		Error: From<B::Error>,
+	{
+		let reads = |readahead, fixed| {
+			let mut r = BufRefReaderBuilder::new(CountingReader { src: WORDS, reads: 0 })
+				.capacity(4096)
+				.fixed(fixed)
+				.readahead(readahead)
+				.build::<B>()
+				.unwrap();
+			let mut rebuilt = vec![];
+			loop {
+				match r.read_until(b'\0') {
+					Ok(Some(chunk)) => rebuilt.extend_from_slice(chunk),
+					Ok(None) => break,
+					Err(Error::BufferFull) => {
+						assert!(fixed);
+						let len = r.buf.len();
+						rebuilt.extend_from_slice(r.consume(len).unwrap());
+					},
+					Err(err) => panic!("{:?}", err),
+				}
+			}
+			assert_eq!(&rebuilt[..], WORDS);
+			r.src.reads
+		};
+		assert!(reads(4, false) < reads(1, false));
+		// fixed buffer cannot grow anyway
+		assert_eq!(reads(4, true), reads(1, true));
+	}
+
+	#[test] fn readahead_vec()  { readahead::<VecBuffer>() }
+	#[test] fn readahead_mmap() { readahead::<MmapBuffer>() }
+
+	fn grow_to<B: Buffer>()
+	where
+		B::Error: Debug,
+		Error: From<B::Error>,
+	{
+		let mut r = BufRefReaderBuilder::new(WORDS)
+			.capacity(4096)
+			.build::<B>()
+			.unwrap();
+		let cap = r.capacity();
+		assert_eq!(r.read(100).unwrap(), Some(&WORDS[..100]));
+		let buffered = r.buffered().to_vec();
+		assert!(!buffered.is_empty());
+
+		r.grow_to(cap*4).unwrap();
+		assert!(r.capacity() >= cap*4);
+		assert_eq!(r.buffered(), &buffered[..]);
+		assert_eq!(r.position(), 100);
 
-See [module-level docs](index.html) for examples.
-*/
-pub struct BufRefReaderBuilder<R> {
-	src: R,
-	bufsize: usize,
-}
-impl<R: Read> BufRefReaderBuilder<R> {
-	/// Creates new builder with given reader and default options.
-	pub fn new(src: R) -> Self {
-		BufRefReaderBuilder {
-			src,
-			bufsize: 8192,
+		// already large enough
+		let cap = r.capacity();
+		r.grow_to(cap/2).unwrap();
+		assert_eq!(r.capacity(), cap);
+
+		let mut rebuilt = WORDS[..100].to_vec();
+		while let Some(chunk) = r.read(1000).unwrap() {
+			rebuilt.extend_from_slice(chunk);
 		}
+		assert_eq!(rebuilt, WORDS);
 	}
 
-	/// Set initial buffer capacity.
-	pub fn capacity(mut self, bufsize: usize) -> Self {
-		self.bufsize = bufsize;
-		self
-	}
+	#[test] fn grow_to_vec()  { grow_to::<VecBuffer>() }
+	#[test] fn grow_to_mmap() { grow_to::<MmapBuffer>() }
+	#[cfg(feature = "mlock")]
+	#[test] fn grow_to_mlock() { grow_to::<MlockBuffer>() }
 
-	/// Create actual reader.
-	pub fn build<B: Buffer>(self) -> Result<BufRefReader<R, B>, B::Error> {
-		Ok(BufRefReader {
-			src: self.src,
-			buf: B::new(self.bufsize)?,
-		})
+	#[test]
+	fn read_line_lossy() {
+		let mut r = BufRefReaderBuilder::new(&b"lorem\r\nips\xffum\n\xd0\xb4\xd0"[..])
+			.build::<VecBuffer>()
+			.unwrap();
+		match r.read_line_lossy().unwrap() {
+			Some(Cow::Borrowed("lorem")) => (),
+			x => panic!("expected borrowed line, got {:?}", x),
+		}
+		match r.read_line_lossy().unwrap() {
+			Some(Cow::Owned(line)) => assert_eq!(line, "ips\u{fffd}um"),
+			x => panic!("expected owned line, got {:?}", x),
+		}
+		// incomplete sequence at EOF
+		assert_eq!(r.read_line_lossy().unwrap().as_deref(), Some("\u{434}\u{fffd}"));
+		assert_eq!(r.read_line_lossy().unwrap(), None);
 	}
-}
 
-quick_error! {
-	/// Error type that reading functions might emit
-	#[derive(Debug)]
-	pub enum Error {
-		/// Error reading from actual reader
-		IO(err: io::Error) { from() }
-		/// Indicates failure to create/grow buffer
-		Buf(err: vmap::Error) { from() }
-	}
-}
-impl From<()> for Error {
-	// VecBuffer never emits errors, it only panics
-	fn from(_: ()) -> Self {
-		unimplemented!()
-	}
-}
+	fn read_paragraph<B: Buffer>()
+	where
+		B::Error: Debug,
+		Error: From<B::Error>,
+	{
+		let data = b"\n\nlorem\nipsum\n\ndolor\r\nsit\r\n\r\n\r\n\n\namet\n\rconsectetur\n\n\n";
+		let mut r = BufRefReaderBuilder::new(OneByteReader(data))
+			.capacity(1)
+			.build::<B>()
+			.unwrap();
+		assert_eq!(r.read_paragraph().unwrap(), Some(&b"lorem\nipsum"[..]));
+		assert_eq!(r.read_paragraph().unwrap(), Some(&b"dolor\r\nsit"[..]));
+		// lone `\r` does not make a line blank
+		assert_eq!(r.read_paragraph().unwrap(), Some(&b"amet\n\rconsectetur"[..]));
+		assert_eq!(r.read_paragraph().unwrap(), None);
 
-impl<R: Read, B: Buffer> BufRefReader<R, B>
-where Error: From<B::Error>
-{
-	/// Creates buffered reader with default options. Look for [`BufRefReaderBuilder`](struct.BufRefReaderBuilder.html) for tweaks.
-	pub fn new(src: R) -> Result<BufRefReader<R, B>, B::Error> {
-		BufRefReaderBuilder::new(src)
-			.build()
+		// no blank line at EOF, or no line terminator at all
+		let mut r = BufRefReaderBuilder::new(&b"lorem\n\nipsum\ndolor\n"[..])
+			.build::<B>()
+			.unwrap();
+		assert_eq!(r.read_paragraph().unwrap(), Some(&b"lorem"[..]));
+		assert_eq!(r.read_paragraph().unwrap(), Some(&b"ipsum\ndolor"[..]));
+		assert_eq!(r.read_paragraph().unwrap(), None);
+		let mut r = BufRefReaderBuilder::new(&b"lorem"[..])
+			.build::<B>()
+			.unwrap();
+		assert_eq!(r.read_paragraph().unwrap(), Some(&b"lorem"[..]));
+		assert_eq!(r.read_paragraph().unwrap(), None);
+		// blank lines only
+		let mut r = BufRefReaderBuilder::new(&b"\n\r\n\n"[..])
+			.build::<B>()
+			.unwrap();
+		assert_eq!(r.read_paragraph().unwrap(), None);
 	}
 
-	// returns Some(where appended data starts within the filled part of the buffer),
-	// or None for EOF
-	#[inline]
-	fn fill(&mut self) -> Result<Option<usize>, Error> {
-		self.buf.enlarge()?;
+	#[test] fn read_paragraph_vec()  { read_paragraph::<VecBuffer>() }
+	#[test] fn read_paragraph_mmap() { read_paragraph::<MmapBuffer>() }
 
-		let old_len = self.buf.len();
+	fn take_buffer<B: Buffer>()
+	where
+		B::Error: Debug,
+		Error: From<B::Error>,
+	{
+		let mut r = BufRefReaderBuilder::new(WORDS)
+			.capacity(4096)
+			.build::<B>()
+			.unwrap();
+		let mut rebuilt = vec![];
+		for _ in 0..10 {
+			rebuilt.extend_from_slice(r.read_until(b'\n').unwrap().unwrap());
+		}
+		let cap = r.capacity();
+		let (src, buf) = r.take_buffer();
+		assert!(!buf.is_empty());
 
-		match self.src.read(self.buf.appendable())? {
-			0 => Ok(None), // EOF
-			n => {
-				self.buf.mark_appended(n);
-				Ok(Some(old_len))
-			}
+		// unconsumed data is returned first, then the rest of `src`
+		let mut r = BufRefReaderBuilder::new(src)
+			.build_with(buf);
+		assert_eq!(r.capacity(), cap);
+		// nothing that was consumed by the previous reader can be put back
+		assert!(matches!(r.unread(1), Err(Error::CannotUnread)));
+		assert_eq!(r.slice(0..1), Some(&r.buffered()[..1]));
+		while let Some(word) = r.read_until(b'\n').unwrap() {
+			rebuilt.extend_from_slice(word);
 		}
+		assert_eq!(rebuilt, WORDS);
+		assert_eq!(r.capacity(), cap);
 	}
 
-	/**
-	Returns requested amount of bytes, or less if EOF prevents reader from fulfilling the request.
+	#[test] fn take_buffer_vec()  { take_buffer::<VecBuffer>() }
+	#[test] fn take_buffer_mmap() { take_buffer::<MmapBuffer>() }
 
-	Returns:
+	fn clone<B: CloneableBuffer>()
+	where
+		B::Error: Debug,
+		Error: From<B::Error>,
+	{
+		let mut r = BufRefReaderBuilder::new(WORDS)
+			.capacity(16)
+			.build::<B>()
+			.unwrap();
+		for _ in 0..100 {
+			r.read_until(b'\n').unwrap();
+		}
+		let position = r.position();
+		let mut r2 = r.clone();
+		assert_eq!(r2.position(), position);
+		assert_eq!(r2.buffered(), r.buffered());
 
-	- `Ok(Some(data))` with, well, data,
-	- `Ok(None)` if no more data is available,
-	- `Err(err)`: see `std::io::Read::read()`
-	*/
-	#[inline]
-	pub fn read(&mut self, n: usize) -> Result<Option<&[u8]>, Error> {
-		while n > self.buf.len() {
-			// fill and expand buffer until either:
-			// - buffer starts holding the requested amount of data
-			// - EOF is reached
-			if self.fill()?.is_none() { break };
+		// original reader goes on without affecting the clone
+		let mut rest = vec![];
+		while let Some(chunk) = r.read(100).unwrap() {
+			rest.extend_from_slice(chunk);
 		}
-		if self.buf.len() == 0 {
-			// reading past EOF
-			Ok(None)
-		} else {
-			let output = self.buf.consume(n);
-			Ok(Some(output))
+		assert_eq!(rest, &WORDS[position as usize..]);
+
+		// clone still holds data that was consumed before it was cloned
+		r2.unread(1).unwrap();
+		let mut rest = vec![];
+		while let Some(word) = r2.read_until(b'\n').unwrap() {
+			rest.extend_from_slice(word);
 		}
+		assert_eq!(rest, &WORDS[position as usize - 1..]);
 	}
 
-	/**
-	Returns bytes up until and including `delim`, or until EOF mark. If no content is available, returns `None`.
-
-	Returns:
+	#[test] fn clone_vec()  { clone::<VecBuffer>() }
+	#[test] fn clone_mmap() { clone::<MmapBuffer>() }
+	#[cfg(feature = "mlock")]
+	#[test] fn clone_mlock() { clone::<MlockBuffer>() }
 
-	- `Ok(Some(data))` with, well, data,
-	- `Ok(None)` if no more data is available,
-	- `Err(err)`: see `std::io::Read::read()`
-	*/
-	#[inline]
-	pub fn read_until(&mut self, delim: u8) -> Result<Option<&[u8]>, Error> {
-		let mut len = None;
-		// position within filled part of the buffer,
-		// from which to continue search for character
-		let mut pos = 0;
-		loop {
-			// fill and expand buffer until either:
-			// - `delim` appears in the buffer
-			// - EOF is reached
-			if let Some(n) = memchr(delim, &self.buf.filled()[pos..]) {
-				len = Some(pos+n);
-				break;
-			}
-			pos = match self.fill()? {
-				None => break, // EOF
-				Some(pos) => pos,
-			};
-		}
+	#[test]
+	fn buffer_kind() {
+		let r = BufRefReader::<_, VecBuffer>::new(&b""[..]).unwrap();
+		assert_eq!(r.buffer_kind(), BufferKind::Vec);
+		let r = BufRefReader::<_, MmapBuffer>::new(&b""[..]).unwrap();
+		assert_eq!(r.buffer_kind(), BufferKind::MmapRing);
+	}
 
-		match len {
-			None => { // EOF
-				if self.buf.len() == 0 {
-					Ok(None)
-				} else {
-					let output = self.buf.consume(self.buf.len());
-					Ok(Some(output))
-				}
-			},
-			Some(len) => {
-				let len = len + 1; // also include matching delimiter
-				let output = self.buf.consume(len);
-				Ok(Some(output))
-			},
-		}
+	#[test]
+	fn buffer_layout() {
+		let mut r = BufRefReaderBuilder::new(&b"lorem ipsum"[..])
+			.capacity(16)
+			.build::<VecBuffer>()
+			.unwrap();
+		r.read(6).unwrap();
+		assert_eq!(r.buffer_layout(), BufferLayout { capacity: 16, start: 6, len: 5, appendable_len: 5 });
 	}
-}
 
-#[cfg(test)]
-static WORDS: &'static [u8] = include_bytes!("/usr/share/dict/words");
+	#[test]
+	fn debug() {
+		let mut r = BufRefReaderBuilder::new(&b"lorem ipsum"[..])
+			.capacity(16)
+			.build::<VecBuffer>()
+			.unwrap();
+		r.read(6).unwrap();
+		assert_eq!(format!("{:?}", r), "BufRefReader { capacity: 16, buffered: 5, position: 6, eof: false, poisoned: false }");
+		assert_eq!(format!("{:?}", r.buf), "VecBuffer { capacity: 16, start: 6, len: 5 }");
 
-#[cfg(test)]
-mod tests {
-	use super::*;
-	use std::fmt::Debug;
+		let mut r = BufRefReaderBuilder::new(&b"lorem ipsum"[..])
+			.capacity(4096)
+			.build::<MmapBuffer>()
+			.unwrap();
+		r.read(6).unwrap();
+		assert_eq!(format!("{:?}", r), "BufRefReader { capacity: 4096, buffered: 5, position: 6, eof: false, poisoned: false }");
+		assert_eq!(format!("{:?}", r.buf), "MmapBuffer { capacity: 4096, start: 6, len: 5 }");
+	}
 
-	fn read_until_empty_lines<B: Buffer>()
+	fn from_bytes<B: Buffer>()
 	where
 		B::Error: Debug,
 		Error: From<B::Error>,
 	{
-		// two spaces, three spaces, two spaces
-		let mut r = BufRefReaderBuilder::new(&b"  lorem   ipsum  "[..])
+		let mut r = BufRefReaderBuilder::from_bytes(b"lorem ipsum".to_vec())
 			.capacity(4)
 			.build::<B>()
 			.unwrap();
-		assert_eq!(r.read_until(b' ').unwrap(), Some(&b" "[..]));
-		assert_eq!(r.read_until(b' ').unwrap(), Some(&b" "[..]));
 		assert_eq!(r.read_until(b' ').unwrap(), Some(&b"lorem "[..]));
-		assert_eq!(r.read_until(b' ').unwrap(), Some(&b" "[..]));
-		assert_eq!(r.read_until(b' ').unwrap(), Some(&b" "[..]));
-		assert_eq!(r.read_until(b' ').unwrap(), Some(&b"ipsum "[..]));
-		assert_eq!(r.read_until(b' ').unwrap(), Some(&b" "[..]));
+		assert_eq!(r.read_until(b' ').unwrap(), Some(&b"ipsum"[..]));
 		assert_eq!(r.read_until(b' ').unwrap(), None);
+
+		let mut r = BufRefReaderBuilder::from_bytes(String::from("lorem ipsum"))
+			.capacity(4)
+			.build::<B>()
+			.unwrap();
+		assert_eq!(r.read(100).unwrap(), Some(&b"lorem ipsum"[..]));
+
+		let mut r = BufRefReaderBuilder::from_bytes("lorem ipsum")
+			.capacity(4)
+			.build::<B>()
+			.unwrap();
+		assert_eq!(r.read_line().unwrap(), Some(&b"lorem ipsum"[..]));
+		assert_eq!(r.read_line().unwrap(), None);
 	}
 
-	#[test] fn read_until_empty_lines_vec()  { read_until_empty_lines::<VecBuffer>() }
-	#[test] fn read_until_empty_lines_mmap() { read_until_empty_lines::<MmapBuffer>() }
+	#[test] fn from_bytes_vec()  { from_bytes::<VecBuffer>() }
+	#[test] fn from_bytes_mmap() { from_bytes::<MmapBuffer>() }
 
-	fn read_until_words<B: Buffer>()
+	fn into_parts<B: Buffer>()
 	where
 		B::Error: Debug,
 		Error: From<B::Error>,
 	{
 		let mut r = BufRefReaderBuilder::new(WORDS)
-			.capacity(4) // rather pointless for MmapBuffer that rounds up to 4096 or whatever (see read_until_words_long)
+			.capacity(16)
 			.build::<B>()
 			.unwrap();
-		let mut words = WORDS.split(|&c| c == b'\n');
-		while let Ok(Some(slice_buf)) = r.read_until(b'\n') {
-			let mut ref_word = words.next().unwrap()
-				.to_vec();
-			ref_word.push(b'\n');
-			assert_eq!(slice_buf, &ref_word[..]);
+		let mut rebuilt = vec![];
+		while let Some(word) = r.read_until(b'\n').unwrap() {
+			rebuilt.extend_from_slice(word);
+			if rebuilt.len() > 1000 { break }
 		}
 
-		// reader: returned immediately after hitting EOF past last b'\n'
-		// words: this is .split(), hence empty string past last b'\n'
-		assert_eq!(words.next(), Some(&b""[..]));
-
-		assert_eq!(words.next(), None);
+		let (src, leftover) = r.into_parts();
+		assert!(!leftover.is_empty());
+		let mut r = BufRefReader::<_, B>::from_parts(src, &leftover, 4).unwrap();
+		assert_eq!(r.position(), 0);
+		assert!(r.capacity() >= leftover.len());
+		while let Some(word) = r.read_until(b'\n').unwrap() {
+			rebuilt.extend_from_slice(word);
+		}
+		assert_eq!(&rebuilt[..], WORDS);
 	}
 
-	#[test] fn read_until_words_vec()  { read_until_words::<VecBuffer>() }
-	#[test] fn read_until_words_mmap() { read_until_words::<MmapBuffer>() }
+	#[test] fn into_parts_vec()  { into_parts::<VecBuffer>() }
+	#[test] fn into_parts_mmap() { into_parts::<MmapBuffer>() }
 
-	// like read_until_words, but splits by rarest character, which is b'Q'
-	// this also ensures that MmapBuffer (that rounds capacity up to the page size right from the beginning) is going to reallocate itself at least once
-	fn read_until_words_long<B: Buffer>()
+	fn stream_position<B: Buffer>()
 	where
 		B::Error: Debug,
 		Error: From<B::Error>,
 	{
-		let mut r = BufRefReaderBuilder::new(WORDS)
-			.capacity(4096)
+		let mut src = io::Cursor::new(WORDS);
+		src.seek(io::SeekFrom::Start(100)).unwrap();
+		let mut r = BufRefReaderBuilder::new(src)
+			.capacity(16)
 			.build::<B>()
 			.unwrap();
-		let mut words = WORDS.split(|&c| c == b'Q').peekable();
-		while let Ok(Some(slice_buf)) = r.read_until(b'Q') {
-			let mut ref_word = words.next().unwrap()
-				.to_vec();
-			if words.peek() != None {
-				ref_word.push(b'Q');
-			}
-			assert_eq!(slice_buf, &ref_word[..]);
+		assert_eq!(r.stream_position().unwrap(), 100);
+		assert_eq!(r.position(), 0);
+
+		for _ in 0..1000 {
+			let line = r.read_until(b'\n').unwrap().unwrap().to_vec();
+			let pos = r.stream_position().unwrap();
+			assert_eq!(pos, 100 + r.position());
+			assert_eq!(&WORDS[pos as usize - line.len() .. pos as usize], &line[..]);
+			// underlying reader is ahead of us by whatever is buffered
+			assert!(r.src.position() >= pos);
 		}
+	}
 
-		assert_eq!(words.next(), None);
+	#[test] fn stream_position_vec()  { stream_position::<VecBuffer>() }
+	#[test] fn stream_position_mmap() { stream_position::<MmapBuffer>() }
+
+	fn stream_position_seeded<B: Buffer>()
+	where
+		B::Error: Debug,
+		Error: From<B::Error>,
+	{
+		// buffered data did not come from the underlying reader
+		let mut r = BufRefReader::<_, B>::from_parts(io::Cursor::new(vec![]), b"abc", 16).unwrap();
+		assert_eq!(r.stream_position().unwrap_err().kind(), io::ErrorKind::InvalidData);
+		match r.seek(io::SeekFrom::Current(0)) {
+			Err(Error::IO(err)) => assert_eq!(err.kind(), io::ErrorKind::InvalidData),
+			x => panic!("expected IO, got {:?}", x),
+		}
+		// nothing is lost
+		assert_eq!(r.read_until(b'\n').unwrap(), Some(&b"abc"[..]));
+		assert_eq!(r.stream_position().unwrap(), 0);
 	}
 
-	#[test] fn read_until_words_long_vec()  { read_until_words_long::<VecBuffer>() }
-	#[test] fn read_until_words_long_mmap() { read_until_words_long::<MmapBuffer>() }
+	#[test] fn stream_position_seeded_vec()  { stream_position_seeded::<VecBuffer>() }
+	#[test] fn stream_position_seeded_mmap() { stream_position_seeded::<MmapBuffer>() }
 
-	fn read<B: Buffer>()
+	fn seek<B: Buffer>()
 	where
 		B::Error: Debug,
 		Error: From<B::Error>,
 	{
-		let mut r = BufRefReaderBuilder::new(&b"lorem ipsum dolor sit amet"[..])
-			.capacity(4)
+		let mut r = BufRefReaderBuilder::new(io::Cursor::new(WORDS))
+			.capacity(16)
 			.build::<B>()
 			.unwrap();
-		assert_eq!(r.read(5).unwrap(), Some(&b"lorem"[..]));
-		assert_eq!(r.read(6).unwrap(), Some(&b" ipsum"[..]));
-		assert_eq!(r.read(1024).unwrap(), Some(&b" dolor sit amet"[..]));
-		assert_eq!(r.read(1).unwrap(), None);
+		let first = r.read_until(b'\n').unwrap().unwrap().len() as u64;
+		let second = r.read_until(b'\n').unwrap().unwrap().to_vec();
+		assert_eq!(r.seek(io::SeekFrom::Start(first)).unwrap(), first);
+		assert_eq!(r.position(), 0);
+		assert_eq!(r.stats(), Stats::default());
+		assert_eq!(r.read_until(b'\n').unwrap(), Some(&second[..]));
+		// relative to what this reader returns, rather than to what it buffered
+		assert_eq!(r.seek(io::SeekFrom::Current(-(second.len() as i64))).unwrap(), first);
+		// previously consumed data is gone
+		assert!(matches!(r.unread(1), Err(Error::CannotUnread)));
+		assert_eq!(r.read_until(b'\n').unwrap(), Some(&second[..]));
+		assert!(matches!(r.seek(io::SeekFrom::Current(-1000)), Err(Error::IO(_))));
 	}
 
-	#[test] fn read_vec()  { read::<VecBuffer>() }
-	#[test] fn read_mmap() { read::<MmapBuffer>() }
+	#[test] fn seek_vec()  { seek::<VecBuffer>() }
+	#[test] fn seek_mmap() { seek::<MmapBuffer>() }
 
-	fn read_words<B: Buffer>(cap: usize, read: usize)
+	#[test]
+	fn validate_after_seek() {
+		// gets truncated right after seeking, as if file was rotated
+		struct ShrinkingReader {
+			src: io::Cursor<Vec<u8>>,
+			shrink_to: Option<usize>,
+		}
+		impl Read for ShrinkingReader {
+			fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+				self.src.read(buf)
+			}
+		}
+		impl Seek for ShrinkingReader {
+			fn seek(&mut self, pos: io::SeekFrom) -> io::Result<u64> {
+				let pos = self.src.seek(pos)?;
+				if let Some(len) = self.shrink_to.take() {
+					self.src.get_mut().truncate(len);
+				}
+				Ok(pos)
+			}
+		}
+		let build = |validate| BufRefReaderBuilder::new(ShrinkingReader { src: io::Cursor::new(b"lorem\nipsum\ndolor\n".to_vec()), shrink_to: None })
+			.capacity(4)
+			.validate_after_seek(validate)
+			.build::<VecBuffer>()
+			.unwrap();
+
+		let mut r = build(true);
+		assert_eq!(r.read_until(b'\n').unwrap(), Some(&b"lorem\n"[..]));
+		// still there
+		assert_eq!(r.seek(io::SeekFrom::Start(0)).unwrap(), 0);
+		assert_eq!(r.read_until(b'\n').unwrap(), Some(&b"lorem\n"[..]));
+		r.src.shrink_to = Some(3);
+		assert!(matches!(r.seek(io::SeekFrom::Start(4)), Err(Error::SourceChanged)));
+
+		// past the end of what was read so far, so there's nothing to validate
+		let mut r = build(true);
+		r.src.shrink_to = Some(3);
+		assert_eq!(r.seek(io::SeekFrom::Start(4)).unwrap(), 4);
+		assert_eq!(r.read_until(b'\n').unwrap(), None);
+
+		// without validation, truncation looks like regular EOF
+		let mut r = build(false);
+		assert_eq!(r.read_until(b'\n').unwrap(), Some(&b"lorem\n"[..]));
+		r.src.shrink_to = Some(3);
+		assert_eq!(r.seek(io::SeekFrom::Start(4)).unwrap(), 4);
+		assert_eq!(r.read_until(b'\n').unwrap(), None);
+	}
+
+	// xorshift64*: good enough to come up with inputs and sequences of operations, and reproducible
+	struct Rng(u64);
+	impl Rng {
+		fn next(&mut self) -> u64 {
+			self.0 ^= self.0 >> 12;
+			self.0 ^= self.0 << 25;
+			self.0 ^= self.0 >> 27;
+			self.0.wrapping_mul(0x2545_f491_4f6c_dd1d)
+		}
+		fn below(&mut self, n: usize) -> usize {
+			(self.next() % n as u64) as usize
+		}
+	}
+
+	// returns whatever amount of data it feels like
+	struct RandomReader<'a> {
+		src: &'a [u8],
+		rng: Rng,
+	}
+	impl Read for RandomReader<'_> {
+		fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+			let n = std::cmp::min(buf.len(), 1 + self.rng.below(32));
+			self.src.read(&mut buf[..n])
+		}
+	}
+
+	/*
+	random operations on random streams, checked against the simplest model of the stream there is:
+	position within the input, which every returned slice has to start at
+	*/
+	fn random_ops<B: Buffer>()
 	where
 		B::Error: Debug,
 		Error: From<B::Error>,
 	{
-		let mut r = BufRefReaderBuilder::new(WORDS)
-			.capacity(cap)
-			.build::<B>()
-			.unwrap();
-		let mut words = WORDS.chunks(read);
-		while let Ok(Some(slice_buf)) = r.read(read) {
-			let ref_word = words.next().unwrap();
-			assert_eq!(slice_buf, ref_word);
+		for seed in 1..=1000 {
+			let mut rng = Rng(seed);
+			let len = rng.below(2000);
+			// small alphabet, so that delimiters are everywhere, and so are their runs
+			let data: Vec<u8> = (0..len).map(|_| b"ab\n\n-"[rng.below(5)]).collect();
+			let mut builder = BufRefReaderBuilder::new(RandomReader { src: &data[..], rng: Rng(seed + 1) })
+				.capacity(1 + rng.below(64));
+			match rng.below(4) {
+				0 => builder = builder.increment(1 + rng.below(16)),
+				1 => builder = builder.readahead(2 + rng.below(4)),
+				2 => builder = builder.min_read(1 + rng.below(16)),
+				_ => (),
+			}
+			let mut r = builder.build::<B>().unwrap();
+
+			let mut pos = 0;
+			let delim = |rng: &mut Rng| b"ab\n-"[rng.below(4)];
+			let until = |pos: usize, delim: u8| memchr(delim, &data[pos..]).map_or(data.len(), |n| pos + n + 1);
+			let mut ops = 0;
+			while pos < data.len() || ops % 10 != 0 {
+				ops += 1;
+				let op = rng.below(9);
+				let (returned, end) = match op {
+					0 => {
+						let n = 1 + rng.below(100);
+						(r.read(n).unwrap(), std::cmp::min(pos + n, data.len()))
+					},
+					1 => {
+						let d = delim(&mut rng);
+						(r.read_until(d).unwrap(), until(pos, d))
+					},
+					2 => {
+						let max = 1 + rng.below(100);
+						let chunk = r.read_some(max).unwrap();
+						let end = pos + chunk.map_or(0, |chunk| chunk.len());
+						assert!(end - pos <= max, "seed {}", seed);
+						assert_eq!(chunk.is_none(), pos == data.len(), "seed {}", seed);
+						(chunk, end)
+					},
+					3 => {
+						let d = delim(&mut rng);
+						let end = until(pos, d);
+						assert_eq!(r.peek_until(d).unwrap().unwrap_or(b""), &data[pos..end], "seed {}", seed);
+						(None, pos)
+					},
+					4 => {
+						let max = rng.below(100);
+						let end = std::cmp::min(pos + max, data.len());
+						assert_eq!(r.sniff(max).unwrap(), &data[pos..end], "seed {}", seed);
+						(None, pos)
+					},
+					5 => {
+						let n = rng.below(20);
+						if r.unread(n).is_ok() {
+							pos -= n;
+						}
+						(None, pos)
+					},
+					6 => {
+						let end = memmem::find(&data[pos..], b"\n\n").map_or(data.len(), |n| pos + n + 2);
+						(r.read_until_bytes(b"\n\n").unwrap(), end)
+					},
+					7 => {
+						let d = delim(&mut rng);
+						let n = 1 + rng.below(4);
+						let end = (0..n).fold(pos, |end, _| if end == data.len() { end } else { until(end, d) });
+						(r.read_until_nth(d, n).unwrap(), end)
+					},
+					_ => {
+						let d = delim(&mut rng);
+						let target = rng.below(50);
+						let from = std::cmp::min(pos + target.saturating_sub(1), data.len());
+						(r.read_batch(d, target).unwrap(), until(from, d))
+					},
+				};
+				if let Some(returned) = returned {
+					assert_eq!(returned, &data[pos..end], "seed {}, op {}", seed, op);
+				} else if end != pos {
+					panic!("seed {}, op {}: expected {:?}, got None", seed, op, &data[pos..end]);
+				}
+				pos = end;
+				assert_eq!(r.position(), pos as u64, "seed {}, op {}", seed, op);
+			}
+			assert_eq!(r.read_until(b'\n').unwrap(), None, "seed {}", seed);
 		}
-		assert_eq!(words.next(), None);
 	}
 
-	#[test] fn read_words_vec_4x3() { read_words::<VecBuffer>(4, 3) }
-	#[test] fn read_words_vec_4x5() { read_words::<VecBuffer>(4, 5) }
-	#[test] fn read_words_mmap_4x3() { read_words::<MmapBuffer>(4, 3) }
-	#[test] fn read_words_mmap_4x5() { read_words::<MmapBuffer>(4, 5) }
+	#[test] fn random_ops_vec()  { random_ops::<VecBuffer>() }
+	#[test] fn random_ops_mmap() { random_ops::<MmapBuffer>() }
+
+	fn assert_send<T: Send>() {}
+	fn assert_sync<T: Sync>() {}
+
+	#[test]
+	fn send_sync() {
+		assert_send::<VecBuffer>();
+		assert_sync::<VecBuffer>();
+		assert_send::<MmapBuffer>();
+		assert_sync::<MmapBuffer>();
+		assert_send::<BufRefReader<&[u8], VecBuffer>>();
+		assert_send::<BufRefReader<&[u8], MmapBuffer>>();
+	}
 }
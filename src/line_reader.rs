@@ -0,0 +1,91 @@
+use std::io::Read;
+
+use crate::{BufRefReader, Buffer, Error};
+
+/**
+Line reader that copies each line into its own reusable buffer.
+
+This deliberately gives up on the main benefit of [`BufRefReader`](struct.BufRefReader.html),
+as every line is copied once more after being read.
+In return, returned lines no longer borrow the reader's buffer, and [`next_line()`](#method.next_line)
+has the same shape as `Iterator::next()`, which makes it a bit easier to use in place of `BufRead::lines()`.
+Unlike the latter, though, no memory is allocated per line: the same `Vec` is reused for every line,
+and it only grows to fit the longest line seen so far.
+
+```
+use buf_ref_reader::*;
+
+# fn main() -> Result<(), Error> {
+let r = BufRefReader::<_, VecBuffer>::new(&b"lorem\nipsum\r\ndolor"[..])?;
+let mut lines = LineReader::new(r);
+while let Some(line) = lines.next_line() {
+	let _line: &[u8] = line?;
+}
+# Ok(())
+# }
+```
+*/
+pub struct LineReader<R, B> {
+	reader: BufRefReader<R, B>,
+	line: Vec<u8>,
+}
+
+impl<R: Read, B: Buffer> LineReader<R, B>
+where Error: From<B::Error>
+{
+	/// Wraps given reader.
+	pub fn new(reader: BufRefReader<R, B>) -> Self {
+		LineReader {
+			reader,
+			line: vec![],
+		}
+	}
+
+	/// Unwraps underlying reader.
+	pub fn into_inner(self) -> BufRefReader<R, B> {
+		self.reader
+	}
+
+	/**
+	Returns next line, stripped of its terminator, just like [`BufRefReader::read_line()`](struct.BufRefReader.html#method.read_line) does.
+
+	Returns:
+
+	- `Some(Ok(data))` with, well, data,
+	- `None` if no more data is available,
+	- `Some(Err(err))`: see `std::io::Read::read()`
+	*/
+	pub fn next_line(&mut self) -> Option<Result<&[u8], Error>> {
+		match self.reader.read_line() {
+			Ok(Some(line)) => {
+				self.line.clear();
+				self.line.extend_from_slice(line);
+				Some(Ok(&self.line))
+			},
+			Ok(None) => None,
+			Err(err) => Some(Err(err)),
+		}
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+	use crate::*;
+
+	#[test]
+	fn next_line() {
+		let r = BufRefReaderBuilder::new(WORDS)
+			.capacity(16)
+			.build::<VecBuffer>()
+			.unwrap();
+		let mut lines = LineReader::new(r);
+		let mut words = WORDS.split(|&c| c == b'\n');
+		while let Some(line) = lines.next_line() {
+			assert_eq!(line.unwrap(), words.next().unwrap());
+		}
+		// this is .split(), hence empty string past last b'\n'
+		assert_eq!(words.next(), Some(&b""[..]));
+		assert_eq!(words.next(), None);
+	}
+}
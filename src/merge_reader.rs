@@ -0,0 +1,145 @@
+use std::io::Read;
+
+use crate::{BufRefReader, Buffer, Error};
+
+/**
+Reader that merges delimited tokens of two [`BufRefReader`](struct.BufRefReader.html)s, e.g. for external merge sort of line-delimited files.
+
+Every call to [`read_until()`](#method.read_until) peeks at the next token of both readers,
+and returns the smaller one (bytewise, without the delimiter, as `LC_ALL=C sort` does),
+consuming it from its own reader only; if the two are equal, the first reader goes first.
+Hence, if both readers yield sorted tokens, so does the merge.
+If they don't, tokens are still returned in full, just not in any particular order.
+
+Tokens are still returned right from the buffer of the reader that produced them, without copying,
+so each one borrows the merge reader until it is dropped.
+
+```
+use buf_ref_reader::*;
+
+# fn main() -> Result<(), Error> {
+let a = BufRefReader::<_, VecBuffer>::new(&b"apple\ncherry\n"[..])?;
+let b = BufRefReader::<_, VecBuffer>::new(&b"banana\ndate"[..])?;
+let mut merge = MergeReader::new(a, b);
+assert_eq!(merge.read_until(b'\n')?, Some(&b"apple\n"[..]));
+assert_eq!(merge.read_until(b'\n')?, Some(&b"banana\n"[..]));
+assert_eq!(merge.read_until(b'\n')?, Some(&b"cherry\n"[..]));
+assert_eq!(merge.read_until(b'\n')?, Some(&b"date"[..]));
+assert_eq!(merge.read_until(b'\n')?, None);
+# Ok(())
+# }
+```
+*/
+pub struct MergeReader<R1, B1, R2 = R1, B2 = B1> {
+	first: BufRefReader<R1, B1>,
+	second: BufRefReader<R2, B2>,
+}
+
+impl<R1: Read, B1: Buffer, R2: Read, B2: Buffer> MergeReader<R1, B1, R2, B2>
+where
+	Error: From<B1::Error>,
+	Error: From<B2::Error>,
+{
+	/// Wraps given readers.
+	pub fn new(first: BufRefReader<R1, B1>, second: BufRefReader<R2, B2>) -> Self {
+		MergeReader {
+			first,
+			second,
+		}
+	}
+
+	/// Unwraps underlying readers.
+	pub fn into_inner(self) -> (BufRefReader<R1, B1>, BufRefReader<R2, B2>) {
+		(self.first, self.second)
+	}
+
+	/**
+	Returns the smaller one of the next tokens of both readers, i.e. bytes up until and including `delim`, or until EOF mark.
+	If no content is available in either of readers, returns `None`.
+
+	Token is consumed by [`BufRefReader::read_until()`](struct.BufRefReader.html#method.read_until) of its own reader,
+	so options of that reader (e.g. [`require_final_delim()`](struct.BufRefReaderBuilder.html#method.require_final_delim)) still apply.
+
+	Returns:
+
+	- `Ok(Some(data))` with, well, data,
+	- `Ok(None)` if no more data is available,
+	- `Err(err)`: see `std::io::Read::read()`
+	*/
+	pub fn read_until(&mut self, delim: u8) -> Result<Option<&[u8]>, Error> {
+		// tokens are only peeked at, as there's no telling which one goes first until both are at hand
+		let first = match (self.first.peek_until(delim)?, self.second.peek_until(delim)?) {
+			(None, None) => return Ok(None),
+			(Some(_), None) => true,
+			(None, Some(_)) => false,
+			(Some(a), Some(b)) => strip(a, delim) <= strip(b, delim),
+		};
+		if first {
+			self.first.read_until(delim)
+		} else {
+			self.second.read_until(delim)
+		}
+	}
+}
+
+// tokens are compared without delimiters, just like lines are, so that e.g. "a\n" goes before "a\tb\n"
+fn strip(token: &[u8], delim: u8) -> &[u8] {
+	token.strip_suffix(&[delim]).unwrap_or(token)
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+	use crate::*;
+
+	#[test]
+	fn read_until() {
+		// two sorted halves of the word list, the second one lacking final delimiter
+		let mut words: Vec<&[u8]> = WORDS.split(|&c| c == b'\n').filter(|word| !word.is_empty()).collect();
+		words.sort();
+		let join = |words: Vec<&[u8]>| words.join(&b'\n');
+		let (even, odd): (Vec<_>, Vec<_>) = words.iter().enumerate().partition(|(i, _)| i % 2 == 0);
+		let even = join(even.into_iter().map(|(_, &word)| word).collect());
+		let odd = join(odd.into_iter().map(|(_, &word)| word).collect());
+		let even = [&even[..], b"\n"].concat();
+
+		let a = BufRefReaderBuilder::new(&even[..])
+			.capacity(16)
+			.build::<VecBuffer>()
+			.unwrap();
+		// readers do not have to be of the same type
+		let b = BufRefReaderBuilder::new(&odd[..])
+			.capacity(16)
+			.build::<MmapBuffer>()
+			.unwrap();
+		let mut merge = MergeReader::new(a, b);
+		let mut words = words.into_iter();
+		while let Some(token) = merge.read_until(b'\n').unwrap() {
+			assert_eq!(token.strip_suffix(b"\n").unwrap_or(token), words.next().unwrap());
+		}
+		assert_eq!(words.next(), None);
+	}
+
+	#[test]
+	fn read_until_ties() {
+		let a = BufRefReader::<_, VecBuffer>::new(&b"a,b,b,c"[..]).unwrap();
+		let b = BufRefReader::<_, VecBuffer>::new(&b"b,b,c,"[..]).unwrap();
+		let mut merge = MergeReader::new(a, b);
+		let mut tokens = vec![];
+		while let Some(token) = merge.read_until(b',').unwrap() {
+			tokens.push(token.to_vec());
+		}
+		// equal tokens are taken from the first reader first, unterminated one included
+		assert_eq!(tokens, [&b"a,"[..], b"b,", b"b,", b"b,", b"b,", b"c", b"c,"]);
+		let (mut a, mut b) = merge.into_inner();
+		assert_eq!(a.read_until(b',').unwrap(), None);
+		assert_eq!(b.read_until(b',').unwrap(), None);
+
+		// one of the readers is empty from the start
+		let a = BufRefReader::<_, VecBuffer>::new(&b""[..]).unwrap();
+		let b = BufRefReader::<_, VecBuffer>::new(&b"lorem\n"[..]).unwrap();
+		let mut merge = MergeReader::new(a, b);
+		assert_eq!(merge.read_until(b'\n').unwrap(), Some(&b"lorem\n"[..]));
+		assert_eq!(merge.read_until(b'\n').unwrap(), None);
+	}
+}